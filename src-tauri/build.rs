@@ -1,3 +1,5 @@
 fn main() {
+    // 只有图形界面需要生成 Tauri 上下文，关闭 gui 特性时跳过
+    #[cfg(feature = "gui")]
     tauri_build::build()
 }
@@ -0,0 +1,55 @@
+//! 命令行入口：解析参数指定的 SQL 文件（未指定时读取标准输入），每行输出一个源表。
+//! 解析失败时把错误打印到标准错误并以非零状态码退出。
+//!
+//! 用法：`parse_hive [--default-db <db>] [file]`
+
+use std::env;
+use std::error::Error;
+use std::io::{self, Read};
+use std::path::Path;
+use std::process::ExitCode;
+
+use tauri_demo::HiveSqlParser;
+
+fn run() -> Result<Vec<String>, Box<dyn Error>> {
+    let mut default_db = None;
+    let mut path = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--default-db" => {
+                default_db = Some(args.next().ok_or("--default-db requires a value")?);
+            }
+            _ if arg.starts_with("--") => return Err(format!("unknown option: {}", arg).into()),
+            _ => path = Some(arg),
+        }
+    }
+    let mut processor = HiveSqlParser::new();
+    if let Some(db) = default_db {
+        processor = processor.with_default_database(&db);
+    }
+    match path {
+        Some(path) => processor.parse_file(Path::new(&path))?,
+        None => {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input)?;
+            processor.parse(&input)?;
+        }
+    }
+    Ok(processor.get_table_names())
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(table_names) => {
+            for name in table_names {
+                println!("{}", name);
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -1,15 +1,135 @@
 use regex::Regex;
-use sqlparser::ast::Expr::{BinaryOp, Exists, InSubquery, Subquery};
+use sqlparser::ast::Expr::{
+    AtTimeZone, Between, BinaryOp, Case, Cast, Collate, Cube, Exists, Function, GroupingSets,
+    InList, InSubquery, IsDistinctFrom, IsNotDistinctFrom, IsNotNull, IsNull, Nested, Rollup,
+    Subquery, UnaryOp,
+};
 use sqlparser::ast::Join;
 use sqlparser::ast::Select;
-use sqlparser::ast::TableFactor::{Derived, Table};
+use sqlparser::ast::TableFactor::{Derived, NestedJoin, Table, TableFunction};
 use sqlparser::ast::{
-    CreateTable, Expr, Insert, ObjectName, Query, SetExpr, Statement, TableWithJoins, With,
+    CommentObject, CreateTable, Delete, Distinct, Expr, FromTable, FunctionArg, FunctionArgExpr,
+    FunctionArguments, GroupByExpr, Insert, JoinConstraint, JoinOperator, MergeAction,
+    MergeInsertKind, ObjectName, ObjectType, Query, SchemaName, SelectItem, SetExpr, Statement,
+    TableFactor, TableWithJoins, Values, With,
 };
-use sqlparser::dialect::HiveDialect;
+use sqlparser::dialect::{HiveDialect, PostgreSqlDialect};
+use sqlparser::keywords::Keyword;
 use sqlparser::parser::Parser;
-use std::collections::HashSet;
+use sqlparser::tokenizer::{Token, Tokenizer, TokenizerError};
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// 单条 SQL 语句的提取结果。
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StatementInfo {
+    /// 清理后的语句文本
+    pub sql: String,
+    /// 该语句读取的源表
+    pub source_tables: Vec<String>,
+    /// 该语句写入的目标表
+    pub target_tables: Vec<String>,
+}
+
+/// 某个预处理步骤对单条语句造成的改动，仅在开启 `with_diagnostics` 时记录。
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreprocessingDiff {
+    /// 语句在本次 `parse` 输入中的序号（从 0 开始，按 `;` 切分后的片段计数）
+    pub statement_index: usize,
+    /// 预处理步骤：`bucket`、`directory_row_format`、`tablesample` 或 `comment`
+    pub step: &'static str,
+    /// 该步骤处理前的语句
+    pub before: String,
+    /// 该步骤处理后的语句
+    pub after: String,
+}
+
+/// `HiveSqlParser::analyze` 的完整提取结果。
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AnalysisResult {
+    /// 所有语句读取的源表
+    pub sources: Vec<String>,
+    /// 所有语句写入的目标表
+    pub targets: Vec<String>,
+    /// INSERT OVERWRITE DIRECTORY 写入的路径
+    pub directories: Vec<String>,
+    /// 源表和目标表涉及的数据库，按首次出现的顺序去重
+    pub databases: Vec<String>,
+    /// 每条语句的提取结果
+    pub statements: Vec<StatementInfo>,
+    /// 解析过程中记录的错误及语句序号
+    pub errors: Vec<(usize, ParseError)>,
+}
+
+/// 表在 SQL 中的使用方式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TableUsage {
+    /// 被读取的源表
+    Read,
+    /// 被写入的目标表
+    Write,
+    /// WITH 子句定义的 CTE，仅在开启 `with_include_ctes` 时出现
+    Cte,
+}
+
+/// 表在 MERGE 语句中的角色。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MergeRole {
+    /// MERGE INTO 的目标表
+    Target,
+    /// USING 子句中的源表
+    Source,
+    /// ON 条件或 WHEN 子句中引用的表
+    ClauseRef,
+}
+
+/// 解析过程中产生的错误。
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    /// 严格模式下，没有 USE 语句时遇到了未指定数据库的表名
+    UnqualifiedWithoutDatabase { table: String },
+    /// 开启 `with_reject_unsupported` 时，遇到了尚不支持提取表名的语句，`kind` 为语句类型名（如 `ShowTables`）
+    UnsupportedStatement { kind: String },
+    /// sqlparser 无法解析的语句，`statement` 为清理后的语句文本，`message` 为 sqlparser 的错误信息
+    Syntax { statement: String, message: String },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::UnqualifiedWithoutDatabase { table } => write!(
+                f,
+                "table `{}` is not qualified with a database and no USE statement was seen",
+                table
+            ),
+            ParseError::UnsupportedStatement { kind } => {
+                write!(f, "unsupported statement kind `{}`", kind)
+            }
+            ParseError::Syntax { statement, message } => {
+                write!(f, "failed to parse statement `{}`: {}", statement, message)
+            }
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+/// 单条语句开始处理前各项结果的长度，语句出错时据此回滚已提取的部分结果。
+struct StatementCheckpoint {
+    target_table_names: usize,
+    temporary_tables: usize,
+    external_locations: usize,
+    merge_table_roles: usize,
+    created_databases: usize,
+    read_then_dropped: usize,
+    possible_cte_leaks: usize,
+    resolved_cte_names: usize,
+    directory_targets: usize,
+    contains_ddl: bool,
+}
 
 #[derive(Debug)]
 pub struct HiveSqlParser {
@@ -17,6 +137,35 @@ pub struct HiveSqlParser {
     all_table_names: Vec<String>,
     table_names: Vec<String>,
     cte_names: HashSet<String>,
+    statements: Vec<StatementInfo>,
+    batch_separators: Vec<String>,
+    temporary_tables: Vec<String>,
+    target_table_names: Vec<String>,
+    contains_ddl: bool,
+    strict: bool,
+    reject_unsupported: bool,
+    database_selected: bool,
+    pending_error: Option<ParseError>,
+    errors: Vec<(usize, ParseError)>,
+    query_depth: usize,
+    max_query_depth: usize,
+    bucket_regex: bool,
+    include_ctes: bool,
+    statement_ctes: Vec<String>,
+    reported_ctes: HashSet<String>,
+    created_databases: Vec<String>,
+    diagnostics: bool,
+    preprocessing_diff: Vec<PreprocessingDiff>,
+    seen_cte_names: HashSet<String>,
+    possible_cte_leaks: Vec<String>,
+    external_locations: Vec<(String, String)>,
+    resolved_cte_names: Vec<String>,
+    identifier_quotes: Vec<char>,
+    merge_table_roles: Vec<(String, MergeRole)>,
+    ignored_statements: Vec<String>,
+    read_then_dropped: Vec<String>,
+    default_database: Option<String>,
+    directory_targets: Vec<String>,
 }
 
 impl Default for HiveSqlParser {
@@ -32,7 +181,300 @@ impl HiveSqlParser {
             all_table_names: Vec::new(),
             table_names: Vec::new(),
             cte_names: HashSet::new(),
+            statements: Vec::new(),
+            batch_separators: Vec::new(),
+            temporary_tables: Vec::new(),
+            target_table_names: Vec::new(),
+            contains_ddl: false,
+            strict: false,
+            reject_unsupported: false,
+            database_selected: false,
+            pending_error: None,
+            errors: Vec::new(),
+            query_depth: 0,
+            max_query_depth: 0,
+            bucket_regex: true,
+            include_ctes: false,
+            statement_ctes: Vec::new(),
+            reported_ctes: HashSet::new(),
+            created_databases: Vec::new(),
+            diagnostics: false,
+            preprocessing_diff: Vec::new(),
+            seen_cte_names: HashSet::new(),
+            possible_cte_leaks: Vec::new(),
+            external_locations: Vec::new(),
+            resolved_cte_names: Vec::new(),
+            identifier_quotes: vec!['`'],
+            merge_table_roles: Vec::new(),
+            ignored_statements: Vec::new(),
+            read_then_dropped: Vec::new(),
+            default_database: None,
+            directory_targets: Vec::new(),
+        }
+    }
+
+    /// 开启严格模式：在没有 USE 语句时遇到未指定数据库的表名，`parse` 返回
+    /// `ParseError::UnqualifiedWithoutDatabase`。
+    pub fn with_strict_mode(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// 遇到不提取表名的语句（如 `SHOW TABLES`）时，`parse` 返回 `ParseError::UnsupportedStatement`，
+    /// 默认关闭。
+    pub fn with_reject_unsupported(mut self, reject: bool) -> Self {
+        self.reject_unsupported = reject;
+        self
+    }
+
+    /// 设置默认数据库，没有 USE 语句时未指定数据库的表名使用该数据库作为前缀。
+    /// 严格模式下视为已经选择了数据库。
+    pub fn with_default_database(mut self, db: &str) -> Self {
+        self.default_database = Some(db.to_lowercase());
+        self.current_database = db.to_lowercase();
+        self.database_selected = true;
+        self
+    }
+
+    /// 设置额外的批次分隔符（如 `GO`、`/`），单独成行时与 `;` 等价，不区分大小写。
+    pub fn with_batch_separators(mut self, separators: &[&str]) -> Self {
+        self.batch_separators = separators.iter().map(|sep| sep.to_string()).collect();
+        self
+    }
+
+    /// 是否用正则移除建表语句中的分桶子句，默认开启。关闭后改为基于词法分析移除，
+    /// 不会误伤字符串中形如 `clustered by (x) into 2 buckets` 的内容。
+    pub fn with_bucket_regex(mut self, enabled: bool) -> Self {
+        self.bucket_regex = enabled;
+        self
+    }
+
+    /// 是否把 CTE 名作为虚拟表加入结果，默认不加入。加入的 CTE 在
+    /// `get_table_usages` 中标记为 `TableUsage::Cte`。
+    pub fn with_include_ctes(mut self, include: bool) -> Self {
+        self.include_ctes = include;
+        self
+    }
+
+    /// 设置标识符的引号字符，默认只有反引号。加入 `'"'` 后双引号包裹的内容按标识符处理
+    /// （与关键字一样转为小写），否则按字符串字面量保留原样。
+    pub fn with_identifier_quotes(mut self, quotes: &[char]) -> Self {
+        self.identifier_quotes = quotes.to_vec();
+        self
+    }
+
+    /// 设置需要跳过的语句类型，名称与 `Statement` 的变体名一致，例如 `"CreateView"`、`"Directory"`。
+    pub fn with_ignored_statements(mut self, kinds: &[&str]) -> Self {
+        self.ignored_statements = kinds.iter().map(|kind| kind.to_string()).collect();
+        self
+    }
+
+    /// 判断字符是否开启一段引号包裹的内容（字符串或标识符）。
+    fn is_quote(&self, c: char) -> bool {
+        matches!(c, '\'' | '"' | '`') || self.identifier_quotes.contains(&c)
+    }
+
+    /// 开启诊断模式：记录每条语句在各个预处理步骤前后的内容，通过
+    /// `get_preprocessing_diff` 查看表名是被哪一步移除的。
+    pub fn with_diagnostics(mut self, diagnostics: bool) -> Self {
+        self.diagnostics = diagnostics;
+        self
+    }
+
+    /// 诊断模式下，记录预处理步骤对语句的改动；内容未变化时不记录。
+    fn record_preprocessing(
+        &mut self,
+        index: usize,
+        step: &'static str,
+        before: &str,
+        after: &str,
+    ) {
+        if self.diagnostics && before != after {
+            self.preprocessing_diff.push(PreprocessingDiff {
+                statement_index: index,
+                step,
+                before: before.to_string(),
+                after: after.to_string(),
+            });
+        }
+    }
+
+    /// 词法分析 SQL，返回 token 以及每个 token 在原文中的起始字节偏移，最后追加原文长度，
+    /// 即第 `i` 个 token 对应 `offsets[i]..offsets[i + 1]`。由 token 还原文本会丢失 `''`
+    /// 这样的转义，需要改写 SQL 时应按偏移从原文中截取。
+    fn tokenize_with_offsets(
+        query: &str,
+        dialect: &HiveDialect,
+    ) -> Result<(Vec<Token>, Vec<usize>), TokenizerError> {
+        let tokens = Tokenizer::new(dialect, query).tokenize_with_location()?;
+        let line_starts: Vec<usize> = std::iter::once(0)
+            .chain(query.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        let mut offsets = Vec::with_capacity(tokens.len() + 1);
+        for token in &tokens {
+            // 行号和列号都从 1 开始，列号按字符计数
+            let start = line_starts[token.location.line as usize - 1];
+            let offset = query[start..]
+                .char_indices()
+                .nth(token.location.column as usize - 1)
+                .map_or(query.len(), |(i, _)| start + i);
+            offsets.push(offset);
+        }
+        offsets.push(query.len());
+        Ok((tokens.into_iter().map(|t| t.token).collect(), offsets))
+    }
+
+    /// 基于词法分析移除 `CLUSTERED BY (...) [SORTED BY (...)] INTO n BUCKETS` 子句，
+    /// sqlparser 暂不支持解析该子句。
+    fn strip_bucket_clause(query: &str, dialect: &HiveDialect) -> String {
+        let Ok((tokens, offsets)) = Self::tokenize_with_offsets(query, dialect) else {
+            return query.to_string();
+        };
+        // 只从原文中删除匹配到的子句，其余内容保持原样
+        let mut result = String::new();
+        let mut copied = 0;
+        let mut i = 0;
+        while i < tokens.len() {
+            match Self::match_bucket_clause(&tokens, i) {
+                Some(end) => {
+                    result.push_str(&query[copied..offsets[i]]);
+                    copied = offsets[end];
+                    i = end;
+                }
+                None => i += 1,
+            }
+        }
+        result.push_str(&query[copied..]);
+        result
+    }
+
+    /// sqlparser 不支持 Hive 的 TABLESAMPLE 子句，采样不影响表名提取，解析前基于词法分析将其移除，
+    /// 不会误伤字符串中形如 `tablesample(x)` 的内容。
+    fn strip_tablesample(query: &str, dialect: &HiveDialect) -> String {
+        let Ok((tokens, offsets)) = Self::tokenize_with_offsets(query, dialect) else {
+            return query.to_string();
+        };
+        // 只从原文中删除 TABLESAMPLE(...)，其余内容保持原样
+        let mut result = String::new();
+        let mut copied = 0;
+        let mut i = 0;
+        while i < tokens.len() {
+            let is_tablesample = matches!(&tokens[i], Token::Word(w) if w.quote_style.is_none() && w.value.eq_ignore_ascii_case("tablesample"));
+            // TABLESAMPLE 之后（跳过空白）紧跟左括号才是采样子句
+            let lparen = is_tablesample
+                .then(|| {
+                    (i + 1..tokens.len()).find(|&j| !matches!(tokens[j], Token::Whitespace(_)))
+                })
+                .flatten()
+                .filter(|&j| tokens[j] == Token::LParen);
+            if let Some(start) = lparen {
+                // 跳过 TABLESAMPLE 及其后成对的括号
+                let mut depth = 0;
+                let mut end = tokens.len();
+                for (j, token) in tokens.iter().enumerate().skip(start) {
+                    match token {
+                        Token::LParen => depth += 1,
+                        Token::RParen => {
+                            depth -= 1;
+                            if depth == 0 {
+                                end = j + 1;
+                                break;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                result.push_str(&query[copied..offsets[i]]);
+                copied = offsets[end];
+                i = end;
+            } else {
+                i += 1;
+            }
         }
+        result.push_str(&query[copied..]);
+        result
+    }
+
+    /// 如果从 `start` 开始是分桶子句，返回子句之后第一个 token 的下标。
+    fn match_bucket_clause(tokens: &[Token], start: usize) -> Option<usize> {
+        let is_word = |idx: usize, word: &str| matches!(tokens.get(idx), Some(Token::Word(w)) if w.quote_style.is_none() && w.value.eq_ignore_ascii_case(word));
+        // 跳过空白和注释，返回下一个有效 token 的下标
+        let next = |idx: usize| {
+            (idx..tokens.len())
+                .find(|&j| !matches!(tokens[j], Token::Whitespace(_)))
+                .unwrap_or(tokens.len())
+        };
+        // 跳过一对括号及其中的内容，返回右括号之后的下标
+        let skip_parens = |idx: usize| {
+            if tokens.get(idx) != Some(&Token::LParen) {
+                return None;
+            }
+            let mut depth = 0;
+            for (j, token) in tokens.iter().enumerate().skip(idx) {
+                match token {
+                    Token::LParen => depth += 1,
+                    Token::RParen => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Some(j + 1);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            None
+        };
+
+        if !is_word(start, "clustered") {
+            return None;
+        }
+        let mut i = next(start + 1);
+        if !is_word(i, "by") {
+            return None;
+        }
+        i = next(skip_parens(next(i + 1))?);
+        if is_word(i, "sorted") {
+            i = next(i + 1);
+            if !is_word(i, "by") {
+                return None;
+            }
+            i = next(skip_parens(next(i + 1))?);
+        }
+        if !is_word(i, "into") {
+            return None;
+        }
+        i = next(i + 1);
+        if !matches!(tokens.get(i), Some(Token::Number(_, _))) {
+            return None;
+        }
+        i = next(i + 1);
+        if !is_word(i, "buckets") {
+            return None;
+        }
+        Some(i + 1)
+    }
+
+    /// 将单独成行的批次分隔符替换为 `;`，使每个批次被独立解析。
+    fn replace_batch_separators(&self, queries: &str) -> String {
+        if self.batch_separators.is_empty() {
+            return queries.to_string();
+        }
+        queries
+            .lines()
+            .map(|line| {
+                let trimmed = line.trim();
+                if self
+                    .batch_separators
+                    .iter()
+                    .any(|sep| sep.eq_ignore_ascii_case(trimmed))
+                {
+                    ";"
+                } else {
+                    line
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
     }
 
     /// 移除 Hive SQL 查询中的注释（包括单行和多行注释）。
@@ -45,15 +487,44 @@ impl HiveSqlParser {
     ///
     /// 返回一个移除了注释的 SQL 字符串。
     fn remove_hive_sql_comments(&mut self, query: &str) -> String {
-        // 正则表达式匹配多行注释 (/* */)
-        let multiline_comment_re = Regex::new(r"(?s)/\*.*?\*/").unwrap();
-        // 正则表达式匹配单行注释 (--)，并匹配到行尾
-        let singleline_comment_re = Regex::new(r"--[^\n]*").unwrap();
-
-        // 先移除多行注释
-        let without_multiline_comments = multiline_comment_re.replace_all(query, "");
-        // 再移除单行注释
-        let without_comments = singleline_comment_re.replace_all(&without_multiline_comments, "");
+        // 逐字符扫描并记录引号状态，字符串和反引号标识符中的 `--`、`/* */` 不视为注释
+        let mut without_comments = String::with_capacity(query.len());
+        let mut quote: Option<char> = None;
+        let mut chars = query.chars().peekable();
+        while let Some(c) = chars.next() {
+            if let Some(q) = quote {
+                without_comments.push(c);
+                // 字符串中的反斜杠会转义下一个字符
+                if c == '\\' && !self.identifier_quotes.contains(&q) {
+                    without_comments.extend(chars.next());
+                } else if c == q {
+                    quote = None;
+                }
+                continue;
+            }
+            match c {
+                c if self.is_quote(c) => {
+                    quote = Some(c);
+                    without_comments.push(c);
+                }
+                // 单行注释 (--)，移除到行尾，保留换行
+                '-' if chars.peek() == Some(&'-') => {
+                    while chars.next_if(|&c| c != '\n').is_some() {}
+                }
+                // 多行注释 (/* */)
+                '/' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    let mut prev = '\0';
+                    for c in chars.by_ref() {
+                        if prev == '*' && c == '/' {
+                            break;
+                        }
+                        prev = c;
+                    }
+                }
+                _ => without_comments.push(c),
+            }
+        }
 
         // 移除可能留下的多余空行
         let cleaned_query = without_comments
@@ -65,96 +536,581 @@ impl HiveSqlParser {
 
         cleaned_query
     }
+    /// 将语句转为小写，但保留单引号、双引号字符串中的原始内容；`identifier_quotes` 包裹的标识符
+    /// 与关键字一样转为小写。注释中的引号不参与配对，避免 `-- don't` 之类的注释影响后续内容。
+    fn lowercase_outside_literals(query: &str, identifier_quotes: &[char]) -> String {
+        let mut result = String::with_capacity(query.len());
+        let mut quote: Option<char> = None;
+        let mut in_line_comment = false;
+        let mut in_block_comment = false;
+        let mut chars = query.chars().peekable();
+        while let Some(c) = chars.next() {
+            match quote {
+                Some(q) if !identifier_quotes.contains(&q) => {
+                    result.push(c);
+                    // 字符串中的反斜杠会转义下一个字符
+                    if c == '\\' {
+                        result.extend(chars.next());
+                    } else if c == q {
+                        quote = None;
+                    }
+                    continue;
+                }
+                Some(q) => {
+                    if c == q {
+                        quote = None;
+                    }
+                }
+                None if in_line_comment => in_line_comment = c != '\n',
+                None if in_block_comment => {
+                    if c == '*' && chars.peek() == Some(&'/') {
+                        result.push(c);
+                        result.extend(chars.next());
+                        in_block_comment = false;
+                        continue;
+                    }
+                }
+                None => match c {
+                    '\'' | '"' => quote = Some(c),
+                    c if identifier_quotes.contains(&c) => quote = Some(c),
+                    '-' if chars.peek() == Some(&'-') => in_line_comment = true,
+                    '/' if chars.peek() == Some(&'*') => {
+                        result.push(c);
+                        result.extend(chars.next());
+                        in_block_comment = true;
+                        continue;
+                    }
+                    _ => {}
+                },
+            }
+            result.extend(c.to_lowercase());
+        }
+        result
+    }
+
+    /// 按 `;` 切分语句，忽略字符串、反引号标识符以及多行注释中的 `;`。
+    fn split_statements<'a>(&self, queries: &'a str) -> Vec<&'a str> {
+        let mut statements = Vec::new();
+        let mut quote: Option<char> = None;
+        let mut in_line_comment = false;
+        let mut in_block_comment = false;
+        let mut start = 0;
+        let mut chars = queries.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            if in_line_comment {
+                // 为兼容已有脚本，行注释中的 `;` 仍然视为语句结束，但其中的引号不再生效
+                if c == ';' {
+                    statements.push(&queries[start..i]);
+                    start = i + 1;
+                }
+                in_line_comment = c != '\n';
+            } else if in_block_comment {
+                if c == '*' && matches!(chars.peek(), Some((_, '/'))) {
+                    chars.next();
+                    in_block_comment = false;
+                }
+            } else if let Some(q) = quote {
+                // 字符串中的反斜杠会转义下一个字符
+                if c == '\\' && !self.identifier_quotes.contains(&q) {
+                    chars.next();
+                } else if c == q {
+                    quote = None;
+                }
+            } else {
+                match c {
+                    c if self.is_quote(c) => quote = Some(c),
+                    '-' if matches!(chars.peek(), Some((_, '-'))) => {
+                        chars.next();
+                        in_line_comment = true;
+                    }
+                    '/' if matches!(chars.peek(), Some((_, '*'))) => {
+                        chars.next();
+                        in_block_comment = true;
+                    }
+                    ';' => {
+                        statements.push(&queries[start..i]);
+                        start = i + 1;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        statements.push(&queries[start..]);
+        statements
+    }
+
+    /// 解析一段 SQL 脚本。多次调用时结果会追加到已有的提取结果中，
+    /// 需要处理互不相关的脚本时先调用 `reset`。
     pub fn parse(&mut self, queries: &str) -> Result<(), Box<dyn Error>> {
+        self.parse_statements(queries, false)
+    }
+
+    /// 宽松模式解析：某条语句出错时记录错误并继续处理后面的语句。
+    /// 返回本次调用中成功解析的语句提取到的源表，以及本次调用产生的错误。
+    pub fn parse_lenient(&mut self, queries: &str) -> (Vec<String>, Vec<ParseError>) {
+        let tables_before = self.all_table_names.len();
+        let errors_before = self.errors.len();
+        // 宽松模式下 parse_statements 不会返回错误
+        let _ = self.parse_statements(queries, true);
+        (
+            self.all_table_names[tables_before..].to_vec(),
+            self.errors[errors_before..]
+                .iter()
+                .map(|(_, e)| e.clone())
+                .collect(),
+        )
+    }
+
+    fn parse_statements(&mut self, queries: &str, lenient: bool) -> Result<(), Box<dyn Error>> {
         let dialect = HiveDialect {};
         let re = Regex::new(
             r"(?s)(partitioned\s+by.*)?clustered\s+by\s*\([^)]+\)\s+into\s+\d+\s+buckets",
         )
         .unwrap();
-        for query in queries.split(';') {
-            let query = query.trim().to_lowercase();
-            let query = re.replace_all(&query, "");
-            let query = self.remove_hive_sql_comments(&query);
-            println!("cleaned query is:{:?}", query);
+        // sqlparser 不支持 INSERT OVERWRITE DIRECTORY 后的 ROW FORMAT 子句，解析前将其移除
+        let directory_row_format_re = Regex::new(
+            r"(?s)(directory\s+'[^']*')\s+row\s+format\s+(?:delimited(?:\s+(?:fields|collection\s+items|map\s+keys|lines)\s+terminated\s+by\s+'[^']*'|\s+escaped\s+by\s+'[^']*'|\s+null\s+defined\s+as\s+'[^']*')*|serde\s+'[^']*'(?:\s+with\s+serdeproperties\s*\([^)]*\))?)",
+        )
+        .unwrap();
+        let queries = self.replace_batch_separators(queries);
+        for (index, query) in self.split_statements(&queries).into_iter().enumerate() {
+            let lowered = Self::lowercase_outside_literals(query.trim(), &self.identifier_quotes);
+            let query = if self.bucket_regex {
+                re.replace_all(&lowered, "").into_owned()
+            } else {
+                Self::strip_bucket_clause(&lowered, &dialect)
+            };
+            self.record_preprocessing(index, "bucket", &lowered, &query);
+            let stripped = directory_row_format_re.replace_all(&query, "$1");
+            self.record_preprocessing(index, "directory_row_format", &query, &stripped);
+            let query = Self::strip_tablesample(&stripped, &dialect);
+            self.record_preprocessing(index, "tablesample", &stripped, &query);
+            let stripped = self.remove_hive_sql_comments(&query);
+            self.record_preprocessing(index, "comment", &query, &stripped);
+            let query = stripped;
             // 忽略空行和配置行
-            if query.is_empty() || query.starts_with("set ") {
+            if query.is_empty() || Self::starts_with_keyword(&query, "set") {
                 continue;
             }
-            if query.starts_with("use ") {
+            if Self::starts_with_keyword(&query, "use") {
                 self.handle_use_database(&query);
             } else {
-                self.handle_query(&query, &dialect)?;
-                self.all_table_names.extend(
-                    self.table_names
-                        .drain(..)
-                        .filter(|name| !self.cte_names.contains(name))
-                        .collect::<Vec<_>>(),
-                );
-                self.cte_names.clear();
+                let checkpoint = self.checkpoint();
+                let result = if Self::starts_with_keyword(&query, "refresh") {
+                    self.handle_refresh(&query, &dialect)
+                } else if Self::starts_with_keyword(&query, "comment") {
+                    self.handle_comment(&query)
+                } else if Self::starts_with_keyword(&query, "from") {
+                    self.handle_multi_insert(&query, &dialect)
+                } else {
+                    self.handle_query(&query, &dialect)
+                };
+                let error: Option<Box<dyn Error>> = match result {
+                    Err(e) => {
+                        self.pending_error = None;
+                        self.errors.push((
+                            index,
+                            ParseError::Syntax {
+                                statement: query.clone(),
+                                message: e.to_string(),
+                            },
+                        ));
+                        Some(e)
+                    }
+                    Ok(()) => self.pending_error.take().map(|e| {
+                        self.errors.push((index, e.clone()));
+                        Box::new(e) as Box<dyn Error>
+                    }),
+                };
+                if let Some(e) = error {
+                    // 丢弃出错语句已提取的部分结果，避免影响后续的解析
+                    self.rollback(&checkpoint);
+                    if !lenient {
+                        return Err(e);
+                    }
+                    // 宽松模式下继续处理后面的语句
+                    continue;
+                }
+                let mut source_tables = self
+                    .table_names
+                    .drain(..)
+                    .filter(|name| !self.cte_names.contains(name))
+                    .collect::<Vec<_>>();
+                for cte_name in self.statement_ctes.drain(..) {
+                    self.reported_ctes.insert(cte_name.clone());
+                    source_tables.push(cte_name);
+                }
+                self.all_table_names.extend(source_tables.iter().cloned());
+                self.statements.push(StatementInfo {
+                    sql: query,
+                    source_tables,
+                    target_tables: self.target_table_names[checkpoint.target_table_names..]
+                        .to_vec(),
+                });
+                // CTE 只在当前语句内可见，记下名字用于检测后续语句的误用
+                self.seen_cte_names.extend(self.cte_names.drain());
             }
         }
         Ok(())
     }
 
+    fn checkpoint(&self) -> StatementCheckpoint {
+        StatementCheckpoint {
+            target_table_names: self.target_table_names.len(),
+            temporary_tables: self.temporary_tables.len(),
+            external_locations: self.external_locations.len(),
+            merge_table_roles: self.merge_table_roles.len(),
+            created_databases: self.created_databases.len(),
+            read_then_dropped: self.read_then_dropped.len(),
+            possible_cte_leaks: self.possible_cte_leaks.len(),
+            resolved_cte_names: self.resolved_cte_names.len(),
+            directory_targets: self.directory_targets.len(),
+            contains_ddl: self.contains_ddl,
+        }
+    }
+
+    fn rollback(&mut self, checkpoint: &StatementCheckpoint) {
+        self.table_names.clear();
+        self.statement_ctes.clear();
+        self.cte_names.clear();
+        self.target_table_names
+            .truncate(checkpoint.target_table_names);
+        self.temporary_tables.truncate(checkpoint.temporary_tables);
+        self.external_locations
+            .truncate(checkpoint.external_locations);
+        self.merge_table_roles
+            .truncate(checkpoint.merge_table_roles);
+        self.created_databases
+            .truncate(checkpoint.created_databases);
+        self.read_then_dropped
+            .truncate(checkpoint.read_then_dropped);
+        self.possible_cte_leaks
+            .truncate(checkpoint.possible_cte_leaks);
+        self.resolved_cte_names
+            .truncate(checkpoint.resolved_cte_names);
+        self.directory_targets
+            .truncate(checkpoint.directory_targets);
+        self.contains_ddl = checkpoint.contains_ddl;
+    }
+
+    /// 清空所有已提取的结果并把当前数据库恢复为 `default`，以便复用同一个解析器处理
+    /// 互不相关的脚本；通过 `with_*` 设置的选项保持不变。
+    pub fn reset(&mut self) {
+        self.current_database = "default".to_string();
+        self.database_selected = false;
+        self.all_table_names.clear();
+        self.table_names.clear();
+        self.cte_names.clear();
+        self.statements.clear();
+        self.temporary_tables.clear();
+        self.target_table_names.clear();
+        self.contains_ddl = false;
+        self.pending_error = None;
+        self.errors.clear();
+        self.query_depth = 0;
+        self.max_query_depth = 0;
+        self.statement_ctes.clear();
+        self.reported_ctes.clear();
+        self.created_databases.clear();
+        self.preprocessing_diff.clear();
+        self.seen_cte_names.clear();
+        self.possible_cte_leaks.clear();
+        self.external_locations.clear();
+        self.resolved_cte_names.clear();
+        self.merge_table_roles.clear();
+        self.read_then_dropped.clear();
+        self.directory_targets.clear();
+    }
+
+    /// 读取并解析 SQL 文件，结果与 `parse` 一样追加到已有的提取结果中。
+    pub fn parse_file(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let content = fs::read_to_string(path)?;
+        self.parse(&content)
+    }
+
+    /// 读取并解析 gzip 压缩的 SQL 文件，需要开启 `flate2` feature。
+    #[cfg(feature = "flate2")]
+    pub fn parse_gzip_file(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+        use std::io::Read;
+        let mut content = String::new();
+        flate2::read::GzDecoder::new(fs::File::open(path)?).read_to_string(&mut content)?;
+        self.parse(&content)
+    }
+
+    /// 只清空已提取的结果，当前数据库恢复为 `with_default_database` 配置的数据库
+    /// （未配置时为 `default`），其余配置保持不变。
+    pub fn clear_results(&mut self) {
+        self.reset();
+        if let Some(db) = &self.default_database {
+            self.current_database = db.clone();
+            self.database_selected = true;
+        }
+    }
+
+    /// 使用一个全新的解析器解析 SQL，直接返回提取到的源表。
+    pub fn parse_once(queries: &str) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut processor = Self::new();
+        processor.parse(queries)?;
+        Ok(processor.get_table_names())
+    }
+
+    /// 使用一个全新的解析器解析 SQL，一次性返回源表、目标表、数据库和逐条语句的结果，
+    /// 不依赖也不修改任何已有解析器的状态。按宽松模式解析，出错的语句记录在 `errors` 中；
+    /// 只有所有语句都出错时才返回第一个错误。
+    pub fn analyze(sql: &str) -> Result<AnalysisResult, ParseError> {
+        let mut processor = Self::new();
+        // 宽松模式下 parse_statements 不会返回错误
+        let _ = processor.parse_statements(sql, true);
+        if processor.statements.is_empty() {
+            if let Some((_, e)) = processor.errors.first() {
+                return Err(e.clone());
+            }
+        }
+        let mut databases: Vec<String> = Vec::new();
+        for name in processor
+            .all_table_names
+            .iter()
+            .chain(&processor.target_table_names)
+        {
+            if let Some((database, _)) = name.rsplit_once('.') {
+                if !databases.iter().any(|d| d == database) {
+                    databases.push(database.to_string());
+                }
+            }
+        }
+        Ok(AnalysisResult {
+            sources: processor.all_table_names,
+            targets: processor.target_table_names,
+            directories: processor.directory_targets,
+            databases,
+            statements: processor.statements,
+            errors: processor.errors,
+        })
+    }
+
+    /// 快速模式：只做词法分析，把 FROM / JOIN / INTO / OVERWRITE / TABLE 之后的标识符当作表名输出
+    /// （`INTO TABLE`、`OVERWRITE TABLE` 中的 `TABLE` 会被跳过），不构建语法树。
+    /// 适合在超大脚本上做类似 grep 的粗略扫描，准确性不如 `parse`：不排除 CTE 名、不补全数据库名、
+    /// 逗号分隔的第二个及之后的表会被漏掉，`create table` 等 DDL 中的表名也会被输出，
+    /// 未加引号又不带数据库前缀的关键字（如 `user`）不会被当作表名。
+    pub fn extract_raw_table_tokens(sql: &str) -> Vec<String> {
+        let tokens = match Tokenizer::new(&HiveDialect {}, sql).tokenize() {
+            Ok(tokens) => tokens,
+            Err(_) => return Vec::new(),
+        };
+        let tokens: Vec<&Token> = tokens
+            .iter()
+            .filter(|token| !matches!(token, Token::Whitespace(_)))
+            .collect();
+        let mut names = Vec::new();
+        let mut i = 0;
+        while i < tokens.len() {
+            let is_table_keyword = matches!(
+                tokens[i],
+                Token::Word(w) if matches!(w.keyword, Keyword::FROM | Keyword::JOIN | Keyword::INTO | Keyword::OVERWRITE | Keyword::TABLE)
+            );
+            let skips_table = matches!(
+                tokens[i],
+                Token::Word(w) if matches!(w.keyword, Keyword::INTO | Keyword::OVERWRITE)
+            );
+            i += 1;
+            if !is_table_keyword {
+                continue;
+            }
+            // Hive 的 `INSERT INTO TABLE t` / `INSERT OVERWRITE TABLE t` 中 TABLE 是可选关键字
+            if skips_table
+                && matches!(tokens.get(i), Some(Token::Word(w)) if w.keyword == Keyword::TABLE)
+            {
+                i += 1;
+            }
+            // 收集 `a.b.c` 形式的标识符，遇到左括号（子查询）时不会收集到任何内容
+            let mut parts = Vec::new();
+            while let Some(Token::Word(w)) = tokens.get(i) {
+                // 单独出现的关键字不是表名，带数据库前缀的部分（如 `default.t`）除外
+                let qualified = !parts.is_empty() || tokens.get(i + 1) == Some(&&Token::Period);
+                if !qualified && w.quote_style.is_none() && w.keyword != Keyword::NoKeyword {
+                    break;
+                }
+                parts.push(w.value.to_lowercase());
+                if tokens.get(i + 1) == Some(&&Token::Period) {
+                    i += 2;
+                } else {
+                    i += 1;
+                    break;
+                }
+            }
+            if !parts.is_empty() {
+                names.push(parts.join("."));
+            }
+        }
+        names
+    }
+
+    /// 判断语句是否以指定关键字开头（忽略大小写，关键字后必须是空白），
+    /// 不依赖 `parse` 中对整条语句的小写化处理。
+    fn starts_with_keyword(query: &str, keyword: &str) -> bool {
+        query
+            .get(..keyword.len())
+            .is_some_and(|head| head.eq_ignore_ascii_case(keyword))
+            && query[keyword.len()..].starts_with(char::is_whitespace)
+    }
+
     fn handle_use_database(&mut self, query: &str) {
         let parts: Vec<&str> = query.split_whitespace().collect();
         if parts.len() == 2 {
-            self.current_database = parts[1].to_string();
-            println!("current_database={:?}", self.current_database);
+            // Hive 的库名不区分大小写，统一记为小写
+            self.current_database = parts[1].to_lowercase();
+            self.database_selected = true;
+        }
+    }
+
+    /// 处理 `REFRESH [TABLE] db.t`，sqlparser 的 Hive 方言不支持该语句，这里手动解析表名。
+    fn handle_refresh(&mut self, query: &str, dialect: &HiveDialect) -> Result<(), Box<dyn Error>> {
+        let mut parser = Parser::new(dialect).try_with_sql(&query["refresh".len()..])?;
+        // TABLE 关键字可省略
+        let _ = parser.parse_keyword(Keyword::TABLE);
+        let name = parser.parse_object_name(false)?;
+        parser.expect_token(&Token::EOF)?;
+        self.add_valid_table_name(&name);
+        Ok(())
+    }
+
+    /// 处理 `COMMENT ON TABLE/COLUMN ... IS ...`，Hive 方言不支持该语句，借用 PostgreSQL 方言解析。
+    fn handle_comment(&mut self, query: &str) -> Result<(), Box<dyn Error>> {
+        for stmt in Parser::parse_sql(&PostgreSqlDialect {}, query)? {
+            self.handle_statment(&stmt);
+        }
+        Ok(())
+    }
+
+    /// 处理 Hive 的多路插入 `FROM src INSERT ... SELECT ... [INSERT ... SELECT ...]`，sqlparser 不支持该语法：
+    /// 共享的 FROM 子句只解析一次，每个 INSERT 分支（不含 FROM 的 INSERT ... SELECT）单独解析。
+    /// 没有 INSERT 分支时按 `FROM src SELECT ...` 处理，两者都不是时交给 `handle_query`。
+    fn handle_multi_insert(
+        &mut self,
+        query: &str,
+        dialect: &HiveDialect,
+    ) -> Result<(), Box<dyn Error>> {
+        let (tokens, offsets) = Self::tokenize_with_offsets(query, dialect)?;
+        // 只在括号外切分，子查询中的内容保持不变
+        let mut depth = 0usize;
+        let mut inserts = Vec::new();
+        let mut first_select = None;
+        for (i, token) in tokens.iter().enumerate() {
+            match token {
+                Token::LParen => depth += 1,
+                Token::RParen => depth = depth.saturating_sub(1),
+                Token::Word(word) if depth == 0 && word.keyword == Keyword::INSERT => {
+                    inserts.push(offsets[i])
+                }
+                Token::Word(word) if depth == 0 && word.keyword == Keyword::SELECT => {
+                    first_select.get_or_insert(offsets[i]);
+                }
+                _ => {}
+            }
+        }
+        // 按原文偏移切分，避免由 token 还原文本时丢失 `''` 这样的转义
+        let branches = if inserts.is_empty() {
+            match first_select {
+                Some(select) => vec![select],
+                None => return self.handle_query(query, dialect),
+            }
+        } else {
+            inserts
+        };
+        self.handle_query(&format!("select * {}", &query[..branches[0]]), dialect)?;
+        for (i, start) in branches.iter().enumerate() {
+            let end = branches.get(i + 1).copied().unwrap_or(query.len());
+            self.handle_query(&query[*start..end], dialect)?;
         }
+        Ok(())
     }
 
     fn handle_query(&mut self, query: &str, dialect: &HiveDialect) -> Result<(), Box<dyn Error>> {
         let ast = Parser::parse_sql(dialect, query)?;
         for stmt in ast {
-            println!("stmt={:?}", stmt);
             self.handle_statment(&stmt);
         }
         Ok(())
     }
 
     fn handle_statment_query(&mut self, query: &Query) {
-        if let SetExpr::Select(select) = &*query.body {
-            // 处理 FROM 子句
-            for table_with_joins in &select.from {
-                if let TableWithJoins {
-                    relation: Table { name, .. },
-                    joins,
-                    ..
-                } = table_with_joins
-                {
-                    println!("Table name: {:?}", name);
-                    self.add_valid_table_name(name);
-                    for j in joins {
-                        match &j.relation {
-                            Table { name, .. } => self.add_valid_table_name(name),
-                            Derived { subquery, .. } => {
-                                self.extract_table_names_from_query(subquery)
-                            }
-                            _ => println!("忽略分支:{:?}", &j.relation),
-                        };
-                    }
-                }
-            }
-        }
+        // 交给完整的查询遍历逻辑，保证 WITH、集合运算和子查询都能被处理
+        self.extract_table_names_from_query(query);
     }
 
     fn handle_statment(&mut self, stmt: &Statement) {
+        if !self.ignored_statements.is_empty() {
+            let kind = Self::statement_kind(stmt);
+            if self
+                .ignored_statements
+                .iter()
+                .any(|ignored| ignored == kind)
+            {
+                return;
+            }
+        }
+        if matches!(
+            stmt,
+            Statement::CreateTable(_)
+                | Statement::CreateView { .. }
+                | Statement::CreateIndex(_)
+                | Statement::CreateDatabase { .. }
+                | Statement::CreateSchema { .. }
+                | Statement::CreateFunction { .. }
+                | Statement::AlterTable { .. }
+                | Statement::AlterView { .. }
+                | Statement::AlterIndex { .. }
+                | Statement::Drop { .. }
+                | Statement::DropFunction { .. }
+                | Statement::Truncate { .. }
+        ) {
+            self.contains_ddl = true;
+        }
         match stmt {
             // 处理 CREATE TABLE AS SELECT 语句
             Statement::CreateTable(CreateTable {
-                query: Some(boxed_query),
+                name,
+                temporary,
+                external,
+                location,
+                hive_formats,
+                query,
+                like,
                 ..
             }) => {
-                self.handle_statment_query(boxed_query);
+                // 记录外部表的 LOCATION 路径
+                if *external {
+                    let hive_location = hive_formats.as_ref().and_then(|f| f.location.as_ref());
+                    if let Some(location) = location.as_ref().or(hive_location) {
+                        self.external_locations
+                            .push((self.get_actual_table_name(name), location.clone()));
+                    }
+                }
+                // 记录会话级临时表，便于从持久化血缘中排除
+                if *temporary {
+                    self.temporary_tables.push(self.get_actual_table_name(name));
+                }
+                self.target_table_names
+                    .push(self.get_actual_table_name(name));
+                if let Some(boxed_query) = query {
+                    self.handle_statment_query(boxed_query);
+                }
+                // CREATE TABLE ... LIKE 引用的模板表记为源表
+                if let Some(template) = like {
+                    self.add_valid_table_name(template);
+                }
             }
 
             // 处理 INSERT INTO ... SELECT 语句
             Statement::Insert(Insert {
-                source: Some(boxed_source),
-                ..
+                table_name, source, ..
             }) => {
-                self.handle_statment_query(boxed_source);
+                self.target_table_names
+                    .push(self.get_actual_table_name(table_name));
+                if let Some(boxed_source) = source {
+                    self.handle_statment_query(boxed_source);
+                }
             }
 
             // 处理普通的查询语句
@@ -166,17 +1122,273 @@ impl HiveSqlParser {
                 self.extract_table_names_from_query(query);
             }
 
-            Statement::Directory { source, .. } => {
+            // INSERT OVERWRITE [LOCAL] DIRECTORY 的输出路径单独记录，不混入目标表
+            Statement::Directory { path, source, .. } => {
+                self.directory_targets.push(path.clone());
                 self.extract_table_names_from_query(source);
             }
 
-            _ => println!("处理statment的默认分支:{:?}", stmt),
+            // 处理 DELETE 语句，被删除数据的表记为写入目标
+            Statement::Delete(Delete {
+                from,
+                using,
+                selection,
+                ..
+            }) => {
+                let (FromTable::WithFromKeyword(tables) | FromTable::WithoutKeyword(tables)) = from;
+                for table_with_joins in tables {
+                    if let Table { name, .. } = &table_with_joins.relation {
+                        self.target_table_names
+                            .push(self.get_actual_table_name(name));
+                    }
+                    self.extract_table_names_from_joins(&table_with_joins.joins);
+                }
+                for table_with_joins in using.iter().flatten() {
+                    self.extract_table_names_from_table_with_joins(table_with_joins);
+                }
+                if let Some(selection) = selection {
+                    self.extract_table_names_from_expr(selection);
+                }
+            }
+
+            // 处理 UPDATE 语句，被更新的表记为写入目标，赋值与条件中引用的表记为源表
+            Statement::Update {
+                table,
+                assignments,
+                from,
+                selection,
+                ..
+            } => {
+                if let Table { name, .. } = &table.relation {
+                    self.target_table_names
+                        .push(self.get_actual_table_name(name));
+                }
+                self.extract_table_names_from_joins(&table.joins);
+                for assignment in assignments {
+                    self.extract_table_names_from_expr(&assignment.value);
+                }
+                if let Some(from) = from {
+                    self.extract_table_names_from_table_with_joins(from);
+                }
+                if let Some(selection) = selection {
+                    self.extract_table_names_from_expr(selection);
+                }
+            }
+
+            // 处理 MERGE INTO 语句，目标表记为写入目标，USING 源及 ON、WHEN 子句中引用的表记为源表
+            Statement::Merge {
+                table,
+                source,
+                on,
+                clauses,
+                ..
+            } => {
+                if let Table { name, .. } = table {
+                    let target = self.get_actual_table_name(name);
+                    self.merge_table_roles
+                        .push((target.clone(), MergeRole::Target));
+                    self.target_table_names.push(target);
+                }
+                let start = self.table_names.len();
+                self.extract_table_names_from_relation(source);
+                let source_end = self.table_names.len();
+                self.extract_table_names_from_expr(on);
+                for clause in clauses {
+                    if let Some(predicate) = &clause.predicate {
+                        self.extract_table_names_from_expr(predicate);
+                    }
+                    match &clause.action {
+                        MergeAction::Insert(insert) => {
+                            if let MergeInsertKind::Values(values) = &insert.kind {
+                                self.extract_table_names_from_values(values);
+                            }
+                        }
+                        MergeAction::Update { assignments } => {
+                            for assignment in assignments {
+                                self.extract_table_names_from_expr(&assignment.value);
+                            }
+                        }
+                        MergeAction::Delete => {}
+                    }
+                }
+                for (index, name) in self.table_names[start..].iter().enumerate() {
+                    let role = if start + index < source_end {
+                        MergeRole::Source
+                    } else {
+                        MergeRole::ClauseRef
+                    };
+                    self.merge_table_roles.push((name.clone(), role));
+                }
+            }
+
+            // DROP TABLE 删除的表如果之前被读取过，记录下来提示血缘中存在先读后删
+            Statement::Drop {
+                object_type: ObjectType::Table,
+                names,
+                ..
+            } => {
+                for name in names {
+                    let table = self.get_actual_table_name(name);
+                    if self.all_table_names.contains(&table)
+                        && !self.read_then_dropped.contains(&table)
+                    {
+                        self.read_then_dropped.push(table);
+                    }
+                }
+            }
+
+            // 记录 CREATE DATABASE / CREATE SCHEMA 创建的数据库
+            Statement::CreateDatabase { db_name, .. }
+            | Statement::CreateSchema {
+                schema_name:
+                    SchemaName::Simple(db_name) | SchemaName::NamedAuthorization(db_name, _),
+                ..
+            } => {
+                self.created_databases
+                    .push(Self::get_name_parts(db_name).join("."));
+            }
+
+            // 处理 COMMENT ON 语句，列注释取列名之前的部分作为表名
+            Statement::Comment {
+                object_type,
+                object_name,
+                ..
+            } => match object_type {
+                CommentObject::Table => self.add_valid_table_name(object_name),
+                CommentObject::Column => {
+                    let parts = &object_name.0[..object_name.0.len().saturating_sub(1)];
+                    self.add_valid_table_name(&ObjectName(parts.to_vec()));
+                }
+            },
+
+            // 处理 MSCK REPAIR TABLE 维护语句
+            Statement::Msck { table_name, .. } => {
+                self.add_valid_table_name(table_name);
+            }
+
+            _ => {
+                if self.reject_unsupported && self.pending_error.is_none() {
+                    self.pending_error = Some(ParseError::UnsupportedStatement {
+                        kind: Self::statement_kind(stmt).to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    /// 取语句的类型名，即 `Statement` 的枚举变体名。
+    fn statement_kind(stmt: &Statement) -> &'static str {
+        match stmt {
+            Statement::Analyze { .. } => "Analyze",
+            Statement::Truncate { .. } => "Truncate",
+            Statement::Msck { .. } => "Msck",
+            Statement::Query(..) => "Query",
+            Statement::Insert(..) => "Insert",
+            Statement::Install { .. } => "Install",
+            Statement::Load { .. } => "Load",
+            Statement::Directory { .. } => "Directory",
+            Statement::Call(..) => "Call",
+            Statement::Copy { .. } => "Copy",
+            Statement::CopyIntoSnowflake { .. } => "CopyIntoSnowflake",
+            Statement::Close { .. } => "Close",
+            Statement::Update { .. } => "Update",
+            Statement::Delete(..) => "Delete",
+            Statement::CreateView { .. } => "CreateView",
+            Statement::CreateTable(..) => "CreateTable",
+            Statement::CreateVirtualTable { .. } => "CreateVirtualTable",
+            Statement::CreateIndex(..) => "CreateIndex",
+            Statement::CreateRole { .. } => "CreateRole",
+            Statement::CreateSecret { .. } => "CreateSecret",
+            Statement::AlterTable { .. } => "AlterTable",
+            Statement::AlterIndex { .. } => "AlterIndex",
+            Statement::AlterView { .. } => "AlterView",
+            Statement::AlterRole { .. } => "AlterRole",
+            Statement::AttachDatabase { .. } => "AttachDatabase",
+            Statement::AttachDuckDBDatabase { .. } => "AttachDuckDBDatabase",
+            Statement::DetachDuckDBDatabase { .. } => "DetachDuckDBDatabase",
+            Statement::Drop { .. } => "Drop",
+            Statement::DropFunction { .. } => "DropFunction",
+            Statement::DropProcedure { .. } => "DropProcedure",
+            Statement::DropSecret { .. } => "DropSecret",
+            Statement::Declare { .. } => "Declare",
+            Statement::CreateExtension { .. } => "CreateExtension",
+            Statement::Fetch { .. } => "Fetch",
+            Statement::Flush { .. } => "Flush",
+            Statement::Discard { .. } => "Discard",
+            Statement::SetRole { .. } => "SetRole",
+            Statement::SetVariable { .. } => "SetVariable",
+            Statement::SetTimeZone { .. } => "SetTimeZone",
+            Statement::SetNames { .. } => "SetNames",
+            Statement::SetNamesDefault { .. } => "SetNamesDefault",
+            Statement::ShowFunctions { .. } => "ShowFunctions",
+            Statement::ShowVariable { .. } => "ShowVariable",
+            Statement::ShowStatus { .. } => "ShowStatus",
+            Statement::ShowVariables { .. } => "ShowVariables",
+            Statement::ShowCreate { .. } => "ShowCreate",
+            Statement::ShowColumns { .. } => "ShowColumns",
+            Statement::ShowTables { .. } => "ShowTables",
+            Statement::ShowCollation { .. } => "ShowCollation",
+            Statement::Use { .. } => "Use",
+            Statement::StartTransaction { .. } => "StartTransaction",
+            Statement::SetTransaction { .. } => "SetTransaction",
+            Statement::Comment { .. } => "Comment",
+            Statement::Commit { .. } => "Commit",
+            Statement::Rollback { .. } => "Rollback",
+            Statement::CreateSchema { .. } => "CreateSchema",
+            Statement::CreateDatabase { .. } => "CreateDatabase",
+            Statement::CreateFunction { .. } => "CreateFunction",
+            Statement::CreateTrigger { .. } => "CreateTrigger",
+            Statement::DropTrigger { .. } => "DropTrigger",
+            Statement::CreateProcedure { .. } => "CreateProcedure",
+            Statement::CreateMacro { .. } => "CreateMacro",
+            Statement::CreateStage { .. } => "CreateStage",
+            Statement::Assert { .. } => "Assert",
+            Statement::Grant { .. } => "Grant",
+            Statement::Revoke { .. } => "Revoke",
+            Statement::Deallocate { .. } => "Deallocate",
+            Statement::Execute { .. } => "Execute",
+            Statement::Prepare { .. } => "Prepare",
+            Statement::Kill { .. } => "Kill",
+            Statement::ExplainTable { .. } => "ExplainTable",
+            Statement::Explain { .. } => "Explain",
+            Statement::Savepoint { .. } => "Savepoint",
+            Statement::ReleaseSavepoint { .. } => "ReleaseSavepoint",
+            Statement::Merge { .. } => "Merge",
+            Statement::Cache { .. } => "Cache",
+            Statement::UNCache { .. } => "UNCache",
+            Statement::CreateSequence { .. } => "CreateSequence",
+            Statement::CreateType { .. } => "CreateType",
+            Statement::Pragma { .. } => "Pragma",
+            Statement::LockTables { .. } => "LockTables",
+            Statement::UnlockTables => "UnlockTables",
+            Statement::Unload { .. } => "Unload",
+            Statement::OptimizeTable { .. } => "OptimizeTable",
         }
     }
 
     fn add_valid_table_name(&mut self, name: &ObjectName) {
+        // 空表名会被拼成 `default.` 这样的非法结果，直接跳过
+        if Self::get_name_parts(name).is_empty() {
+            return;
+        }
         let origin_table_name = self.get_origin_table_name(name);
         if !self.cte_names.contains(&origin_table_name) {
+            // 前面语句定义过的同名 CTE 在这里并不可见，可能是误把 CTE 当成了真实表
+            if Self::get_name_parts(name).len() == 1
+                && self.seen_cte_names.contains(&origin_table_name)
+                && !self.possible_cte_leaks.contains(&origin_table_name)
+            {
+                self.possible_cte_leaks.push(origin_table_name.clone());
+            }
+            if self.strict
+                && Self::get_name_parts(name).len() == 1
+                && !self.database_selected
+                && self.pending_error.is_none()
+            {
+                self.pending_error = Some(ParseError::UnqualifiedWithoutDatabase {
+                    table: origin_table_name,
+                });
+            }
             self.table_names.push(self.get_actual_table_name(name));
         }
     }
@@ -184,91 +1396,218 @@ impl HiveSqlParser {
     fn extract_cte_names(&mut self, with: &With) {
         for cte in &with.cte_tables {
             self.cte_names.insert(cte.alias.name.to_string());
+            if !self.resolved_cte_names.contains(&cte.alias.name.value) {
+                self.resolved_cte_names.push(cte.alias.name.value.clone());
+            }
+            if self.include_ctes {
+                self.statement_ctes.push(cte.alias.name.value.clone());
+            }
             self.extract_table_names_from_query(&cte.query);
         }
     }
 
+    fn extract_table_names_from_relation(&mut self, relation: &TableFactor) {
+        match relation {
+            // 带参数的是表值函数，函数名不是表，只处理参数中的子查询
+            Table {
+                args: Some(args), ..
+            } => self.extract_table_names_from_function_args(&args.args),
+            Table { name, .. } => self.add_valid_table_name(name),
+            Derived { subquery, .. } => self.extract_table_names_from_query(subquery),
+            // 括号包裹的 JOIN，例如 `FROM (a JOIN b)`
+            NestedJoin {
+                table_with_joins, ..
+            } => self.extract_table_names_from_table_with_joins(table_with_joins),
+            // `TABLE(<expr>)` 形式的表函数，表达式中可能包含子查询
+            TableFunction { expr, .. } => self.extract_table_names_from_expr(expr),
+            _ => {}
+        };
+    }
     fn extract_table_names_from_joins(&mut self, joins: &Vec<Join>) {
         for join in joins {
-            match &join.relation {
-                Table { name, .. } => self.add_valid_table_name(name),
-                Derived { subquery, .. } => self.extract_table_names_from_query(subquery),
-                _ => println!("处理joins的relation的默认分支:{:?}", &join.relation),
+            self.extract_table_names_from_relation(&join.relation);
+            // ON 条件中可能包含子查询
+            let constraint = match &join.join_operator {
+                JoinOperator::Inner(constraint)
+                | JoinOperator::LeftOuter(constraint)
+                | JoinOperator::RightOuter(constraint)
+                | JoinOperator::FullOuter(constraint)
+                | JoinOperator::LeftSemi(constraint)
+                | JoinOperator::RightSemi(constraint)
+                | JoinOperator::LeftAnti(constraint)
+                | JoinOperator::RightAnti(constraint)
+                | JoinOperator::AsOf { constraint, .. } => Some(constraint),
+                _ => None,
             };
+            if let Some(JoinConstraint::On(on)) = constraint {
+                self.extract_table_names_from_expr(on);
+            }
         }
     }
 
     fn extract_table_names_from_expr(&mut self, expr: &Expr) {
         match expr {
-            Subquery(subquery) => {
+            Subquery(subquery) | Exists { subquery, .. } => {
                 self.extract_table_names_from_query(subquery);
             }
-            _ => println!("expr默认分支:{:?}", expr),
-        };
-    }
-
-    fn extract_table_names_from_select(&mut self, select: &Select) {
-        for table_with_joins in &select.from {
-            match table_with_joins {
-                TableWithJoins {
-                    relation: Table { name, .. },
-                    joins,
-                    ..
-                } => {
-                    self.add_valid_table_name(name);
-                    self.extract_table_names_from_joins(joins);
-                }
-                TableWithJoins {
-                    relation: Derived { subquery, .. },
-                    joins,
-                    ..
-                } => {
-                    self.extract_table_names_from_query(subquery);
-                    self.extract_table_names_from_joins(joins);
-                }
-                _ => println!("table_with_joins默认分支:{:?}", table_with_joins),
-            };
-        }
-        // 处理where子查询
-        match &select.selection {
-            Some(Exists { subquery, .. }) | Some(InSubquery { subquery, .. }) => {
+            InSubquery { expr, subquery, .. } => {
+                self.extract_table_names_from_expr(expr);
                 self.extract_table_names_from_query(subquery);
             }
-            Some(BinaryOp { right, left, .. }) => {
+            InList { expr, list, .. } => {
+                self.extract_table_names_from_expr(expr);
+                for item in list {
+                    self.extract_table_names_from_expr(item);
+                }
+            }
+            BinaryOp { left, right, .. } => {
+                self.extract_table_names_from_expr(left);
                 self.extract_table_names_from_expr(right);
+            }
+            IsDistinctFrom(left, right) | IsNotDistinctFrom(left, right) => {
                 self.extract_table_names_from_expr(left);
+                self.extract_table_names_from_expr(right);
             }
-            _ => {
-                println!("select.selection默认分支:{:?}", select.selection);
+            AtTimeZone {
+                timestamp,
+                time_zone,
+            } => {
+                self.extract_table_names_from_expr(timestamp);
+                self.extract_table_names_from_expr(time_zone);
+            }
+            Collate { expr, .. }
+            | UnaryOp { expr, .. }
+            | Cast { expr, .. }
+            | Nested(expr)
+            | IsNull(expr)
+            | IsNotNull(expr) => self.extract_table_names_from_expr(expr),
+            Between {
+                expr, low, high, ..
+            } => {
+                self.extract_table_names_from_expr(expr);
+                self.extract_table_names_from_expr(low);
+                self.extract_table_names_from_expr(high);
+            }
+            // 处理函数参数中的子查询，如 `coalesce((select ...), 0)`
+            Function(function) => match &function.args {
+                FunctionArguments::List(list) => {
+                    self.extract_table_names_from_function_args(&list.args)
+                }
+                FunctionArguments::Subquery(subquery) => {
+                    self.extract_table_names_from_query(subquery)
+                }
+                FunctionArguments::None => {}
+            },
+            // GROUPING SETS / CUBE / ROLLUP 的每个分组成员（Hive 方言暂不能解析 GROUPING SETS）
+            GroupingSets(sets) | Cube(sets) | Rollup(sets) => {
+                for expr in sets.iter().flatten() {
+                    self.extract_table_names_from_expr(expr);
+                }
+            }
+            Case {
+                operand,
+                conditions,
+                results,
+                else_result,
+            } => {
+                if let Some(operand) = operand {
+                    self.extract_table_names_from_expr(operand);
+                }
+                for (condition, result) in conditions.iter().zip(results) {
+                    self.extract_table_names_from_expr(condition);
+                    self.extract_table_names_from_expr(result);
+                }
+                if let Some(else_result) = else_result {
+                    self.extract_table_names_from_expr(else_result);
+                }
+            }
+            _ => {}
+        };
+    }
+
+    fn extract_table_names_from_function_args(&mut self, args: &[FunctionArg]) {
+        for arg in args {
+            if let FunctionArg::Named {
+                arg: FunctionArgExpr::Expr(expr),
+                ..
+            }
+            | FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) = arg
+            {
+                self.extract_table_names_from_expr(expr);
             }
         }
+    }
 
-        match &select.having {
-            Some(BinaryOp { right, left, .. }) => {
-                self.extract_table_names_from_expr(right);
-                self.extract_table_names_from_expr(left);
+    fn extract_table_names_from_table_with_joins(&mut self, table_with_joins: &TableWithJoins) {
+        self.extract_table_names_from_relation(&table_with_joins.relation);
+        self.extract_table_names_from_joins(&table_with_joins.joins);
+    }
+
+    fn extract_table_names_from_select(&mut self, select: &Select) {
+        // 处理 DISTINCT ON (...) 中的子查询
+        if let Some(Distinct::On(exprs)) = &select.distinct {
+            for expr in exprs {
+                self.extract_table_names_from_expr(expr);
             }
-            _ => {
-                println!("select.having默认分支:{:?}", select.having);
+        }
+        // 处理投影中的标量子查询
+        for item in &select.projection {
+            if let SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } = item {
+                self.extract_table_names_from_expr(expr);
+            }
+        }
+        for table_with_joins in &select.from {
+            self.extract_table_names_from_table_with_joins(table_with_joins);
+        }
+        // 处理 where、group by 和 having 中的子查询
+        if let Some(selection) = &select.selection {
+            self.extract_table_names_from_expr(selection);
+        }
+        if let GroupByExpr::Expressions(exprs, _) = &select.group_by {
+            for expr in exprs {
+                self.extract_table_names_from_expr(expr);
+            }
+        }
+        if let Some(having) = &select.having {
+            self.extract_table_names_from_expr(having);
+        }
+    }
+
+    fn extract_table_names_from_values(&mut self, values: &Values) {
+        // VALUES 本身不引用表，只需处理其中可能出现的子查询
+        for row in &values.rows {
+            for expr in row {
+                self.extract_table_names_from_expr(expr);
             }
         }
     }
 
     fn extract_table_names_from_set_option(&mut self, node: &SetExpr) {
-        match node {
-            SetExpr::SetOperation { left, right, .. } => {
-                self.extract_table_names_from_set_option(left);
-                self.extract_table_names_from_set_option(right);
+        // 用显式栈代替递归遍历集合运算，避免超长的 UNION 链导致调用栈过深
+        let mut stack = vec![node];
+        while let Some(node) = stack.pop() {
+            match node {
+                SetExpr::SetOperation { left, right, .. } => {
+                    // 先压入右侧，保证左侧先出栈，维持表名的出现顺序
+                    stack.push(right);
+                    stack.push(left);
+                }
+                SetExpr::Select(select) => self.extract_table_names_from_select(select),
+                SetExpr::Query(query) => self.extract_table_names_from_query(query),
+                SetExpr::Values(values) => self.extract_table_names_from_values(values),
+                _ => {}
             }
-            SetExpr::Select(select) => self.extract_table_names_from_select(select),
-            _ => println!(
-                "extract_table_names_from_set_option node默认分支:{:?}",
-                node
-            ),
         }
     }
 
+    /// 进入一层 Query，最外层查询为第 1 层，每嵌套一层子查询（包括 CTE 和派生表）加一。
+    fn enter_query(&mut self) {
+        self.query_depth += 1;
+        self.max_query_depth = self.max_query_depth.max(self.query_depth);
+    }
+
     fn extract_table_names_from_query(&mut self, query: &Query) {
+        self.enter_query();
         // 处理 WITH 子句
         if let Some(with) = &query.with {
             self.extract_cte_names(with);
@@ -276,27 +1615,29 @@ impl HiveSqlParser {
         match &*query.body {
             SetExpr::Select(select) => self.extract_table_names_from_select(select),
             SetExpr::Query(query) => self.extract_table_names_from_query(query),
-            // 处理 INSERT INTO ... SELECT ... 语句
+            // 处理 INSERT INTO ... SELECT ... 语句；CTE 体为 INSERT 时也走这里，
+            // handle_statment 只记录一次写入目标并遍历一次 source，不会重复计数
             SetExpr::Insert(insert) => self.handle_statment(insert),
-            SetExpr::SetOperation { left, right, .. } => {
-                self.extract_table_names_from_set_option(left);
-                self.extract_table_names_from_set_option(right);
-            }
-            _ => println!(
-                "extract_table_names_from_query query.body默认分支:{:?}",
-                &*query.body
-            ),
+            SetExpr::SetOperation { .. } => self.extract_table_names_from_set_option(&query.body),
+            SetExpr::Values(values) => self.extract_table_names_from_values(values),
+            _ => {}
         }
+        self.query_depth -= 1;
     }
 
-    fn get_actual_table_name(&self, name: &ObjectName) -> String {
-        let name_parts = name
-            .0
+    /// 返回表名的各个组成部分，忽略形如 `` test.``.t `` 中的空标识符。
+    fn get_name_parts(name: &ObjectName) -> Vec<String> {
+        name.0
             .iter()
+            .filter(|ident| !ident.value.is_empty())
             .map(|ident| ident.value.clone())
-            .collect::<Vec<_>>();
-        if name_parts.len() == 2 {
-            // 如果表名已经包含了数据库名
+            .collect()
+    }
+
+    fn get_actual_table_name(&self, name: &ObjectName) -> String {
+        let name_parts = Self::get_name_parts(name);
+        if name_parts.len() >= 2 {
+            // 如果表名已经包含了数据库名（或者是 catalog.db.table 这样的三段式名称）
             name_parts.join(".")
         } else {
             // 否则加上当前的数据库名
@@ -304,1071 +1645,2919 @@ impl HiveSqlParser {
         }
     }
 
-    fn get_origin_table_name(&self, name: &ObjectName) -> String {
-        name.0
-            .iter()
-            .map(|ident| ident.value.clone())
-            .collect::<String>()
+    fn get_origin_table_name(&self, name: &ObjectName) -> String {
+        Self::get_name_parts(name).concat()
+    }
+
+    pub fn get_table_names(&self) -> Vec<String> {
+        self.all_table_names.clone()
+    }
+
+    /// 返回源表及产生它的语句序号。序号与 `get_statements()`、`sources_for_statement` 的下标一致，
+    /// 即从 0 开始，不计入空语句、SET、USE 以及解析出错的语句。
+    pub fn get_table_names_with_stmt_index(&self) -> Vec<(usize, String)> {
+        self.statements
+            .iter()
+            .enumerate()
+            .flat_map(|(index, statement)| {
+                statement
+                    .source_tables
+                    .iter()
+                    .map(move |name| (index, name.clone()))
+            })
+            .collect()
+    }
+
+    /// 返回去重后的源表，保留首次出现的顺序；`get_table_names` 仍返回包含重复项的原始结果。
+    pub fn get_unique_table_names(&self) -> Vec<String> {
+        let mut seen = HashSet::new();
+        self.all_table_names
+            .iter()
+            .filter(|name| seen.insert(name.as_str()))
+            .cloned()
+            .collect()
+    }
+
+    /// 返回所有表及其使用方式：先按出现顺序列出读取的表（以及开启 `with_include_ctes`
+    /// 时的 CTE），再列出写入的表。
+    pub fn get_table_usages(&self) -> Vec<(String, TableUsage)> {
+        let reads = self.all_table_names.iter().map(|name| {
+            let usage = if self.reported_ctes.contains(name) {
+                TableUsage::Cte
+            } else {
+                TableUsage::Read
+            };
+            (name.clone(), usage)
+        });
+        let writes = self
+            .target_table_names
+            .iter()
+            .map(|name| (name.clone(), TableUsage::Write));
+        reads.chain(writes).collect()
+    }
+
+    /// 判断是否读取了指定的表，比较时忽略大小写和标识符引号。
+    pub fn references_table(&self, name: &str) -> bool {
+        let normalize = |name: &str| name.replace(['`', '"'], "").to_lowercase();
+        let expected = normalize(name);
+        self.all_table_names
+            .iter()
+            .any(|table| normalize(table) == expected)
+    }
+
+    /// 按数据库分组返回读写的表，值为 `(读取的表, 写入的表)`，表名不带数据库前缀。
+    pub fn get_grouped(&self) -> HashMap<String, (Vec<String>, Vec<String>)> {
+        let mut grouped: HashMap<String, (Vec<String>, Vec<String>)> = HashMap::new();
+        for (names, is_write) in [
+            (&self.all_table_names, false),
+            (&self.target_table_names, true),
+        ] {
+            for name in names {
+                let (database, table) = name.rsplit_once('.').unwrap_or(("", name));
+                let (reads, writes) = grouped.entry(database.to_string()).or_default();
+                let tables = if is_write { writes } else { reads };
+                if !tables.iter().any(|t| t == table) {
+                    tables.push(table.to_string());
+                }
+            }
+        }
+        grouped
+    }
+
+    /// 返回解析过程中达到的最大查询嵌套深度，最外层查询为第 1 层。
+    pub fn get_max_depth(&self) -> usize {
+        self.max_query_depth
+    }
+
+    /// 是否解析到了 DDL 语句（CREATE/DROP/ALTER/TRUNCATE）。
+    pub fn contains_ddl(&self) -> bool {
+        self.contains_ddl
+    }
+
+    /// 返回被写入的目标表（INSERT、CREATE TABLE、DELETE 等语句的目标），
+    /// 与 `get_table_names` 返回的源表互不混合。
+    pub fn get_target_table_names(&self) -> Vec<String> {
+        self.target_table_names.clone()
+    }
+
+    /// 返回 INSERT OVERWRITE [LOCAL] DIRECTORY 写入的路径。
+    pub fn get_directory_targets(&self) -> Vec<String> {
+        self.directory_targets.clone()
+    }
+
+    /// 返回 `CREATE EXTERNAL TABLE ... LOCATION '...'` 中的 `(表名, 存储路径)`。
+    pub fn get_external_locations(&self) -> Vec<(String, String)> {
+        self.external_locations.clone()
+    }
+
+    /// 返回先被读取、之后又被 `DROP TABLE` 删除的表，按删除顺序排列。
+    pub fn get_read_then_dropped(&self) -> Vec<String> {
+        self.read_then_dropped.clone()
+    }
+
+    /// 返回 MERGE 语句中涉及的表及其角色，按目标表、源表、子句引用的顺序排列。
+    pub fn get_merge_table_roles(&self) -> Vec<(String, MergeRole)> {
+        self.merge_table_roles.clone()
+    }
+
+    /// 返回通过 `CREATE DATABASE` / `CREATE SCHEMA` 创建的数据库。
+    pub fn get_created_databases(&self) -> Vec<String> {
+        self.created_databases.clone()
+    }
+
+    /// 返回通过 `CREATE TEMPORARY TABLE` 创建的临时表。
+    pub fn get_temporary_tables(&self) -> Vec<String> {
+        self.temporary_tables.clone()
+    }
+
+    /// 按顺序返回每条已解析语句的提取结果（不含 USE/SET 语句）。
+    pub fn get_statements(&self) -> &[StatementInfo] {
+        &self.statements
+    }
+
+    /// 返回历次 `parse` 产生的错误及出错语句的序号（从 0 开始，按 `;` 切分后的片段计数），
+    /// 按出现顺序排列。
+    pub fn get_errors(&self) -> &[(usize, ParseError)] {
+        &self.errors
+    }
+
+    /// 返回解析过程中识别出的所有 CTE 名（这些名字不会作为源表输出），按首次出现的顺序去重。
+    pub fn get_cte_names(&self) -> Vec<String> {
+        self.resolved_cte_names.clone()
+    }
+
+    /// 返回疑似跨语句引用的 CTE 名：这些名字在前面的语句中被定义为 CTE，
+    /// 却在后面的语句中作为普通表引用（CTE 只在定义它的语句内可见）。
+    pub fn get_possible_cte_leaks(&self) -> Vec<String> {
+        self.possible_cte_leaks.clone()
+    }
+
+    /// 返回诊断模式下记录的预处理改动，按语句和步骤顺序排列。
+    pub fn get_preprocessing_diff(&self) -> &[PreprocessingDiff] {
+        &self.preprocessing_diff
+    }
+
+    /// 返回最早出现的 `ParseError`，便于需要快速失败的调用方使用。
+    pub fn first_error(&self) -> Option<&ParseError> {
+        self.errors.first().map(|(_, e)| e)
+    }
+
+    /// 按语句间的写后读依赖对所有表做拓扑排序，返回 `(有序表名, 为打破环而提前输出的表)`。
+    /// 没有依赖关系的表按首次出现的顺序排列，自引用（如 `insert overwrite t select * from t`）也记为环。
+    pub fn get_tables_topo_order(&self) -> (Vec<String>, Vec<String>) {
+        fn index_of(nodes: &mut Vec<String>, name: &str) -> usize {
+            nodes.iter().position(|n| n == name).unwrap_or_else(|| {
+                nodes.push(name.to_string());
+                nodes.len() - 1
+            })
+        }
+
+        let mut nodes: Vec<String> = Vec::new();
+        let mut edges: HashSet<(usize, usize)> = HashSet::new();
+        let mut cycles: Vec<String> = Vec::new();
+        for stmt in &self.statements {
+            for source in &stmt.source_tables {
+                let from = index_of(&mut nodes, source);
+                for target in &stmt.target_tables {
+                    let to = index_of(&mut nodes, target);
+                    if from == to {
+                        if !cycles.contains(source) {
+                            cycles.push(source.clone());
+                        }
+                    } else {
+                        edges.insert((from, to));
+                    }
+                }
+            }
+            for target in &stmt.target_tables {
+                index_of(&mut nodes, target);
+            }
+        }
+
+        let mut in_degree = vec![0; nodes.len()];
+        for &(_, to) in &edges {
+            in_degree[to] += 1;
+        }
+        let mut emitted = vec![false; nodes.len()];
+        let mut order = Vec::with_capacity(nodes.len());
+        while order.len() < nodes.len() {
+            // 优先输出入度为 0 的表；剩余的表都在环上时，按首次出现顺序强制输出一个
+            let next = match (0..nodes.len()).find(|&i| !emitted[i] && in_degree[i] == 0) {
+                Some(i) => i,
+                None => {
+                    let i = (0..nodes.len()).find(|&i| !emitted[i]).unwrap();
+                    if !cycles.contains(&nodes[i]) {
+                        cycles.push(nodes[i].clone());
+                    }
+                    i
+                }
+            };
+            emitted[next] = true;
+            order.push(nodes[next].clone());
+            for &(from, to) in &edges {
+                if from == next && !emitted[to] {
+                    in_degree[to] -= 1;
+                }
+            }
+        }
+        (order, cycles)
+    }
+
+    /// 返回第 `idx` 条语句（从 0 开始计数）读取的源表，越界时返回 `None`。
+    pub fn sources_for_statement(&self, idx: usize) -> Option<Vec<String>> {
+        self.statements
+            .get(idx)
+            .map(|stmt| stmt.source_tables.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_use_between_queries() {
+        let query =
+            "select id, name from test.my_table where id > 10; select * from test.another_table";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(2, table_names.len());
+        assert!(table_names.contains(&"test.my_table".to_string()));
+        assert!(table_names.contains(&"test.another_table".to_string()));
+    }
+
+    #[test]
+    fn test_parse_create_table() {
+        let query = "create table if not exists test.my_table(id int, name string)";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(0, table_names.len());
+    }
+
+    #[test]
+    fn test_parse_select_query() {
+        let query = "select id, name from test.my_table where id > 10";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(1, table_names.len());
+        assert_eq!("test.my_table", table_names[0]);
+    }
+
+    #[test]
+    fn test_parse_insert_query() {
+        let query = "insert into test.my_table values (1, 'John')";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(0, table_names.len());
+        // Uncomment if insert queries should be handled
+        // assert_eq!("test.my_table", table_names[0]);
+    }
+
+    #[test]
+    fn test_parse_create_table_as_select() {
+        let query = "create table test.new_table as select id, name from test.my_table";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(1, table_names.len());
+        assert!(table_names.contains(&"test.my_table".to_string()));
+    }
+
+    #[test]
+    fn test_parse_with_clause() {
+        let query =
+            "with cte_table as (select id, name from test.my_table) select * from cte_table";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(1, table_names.len());
+        assert!(table_names.contains(&"test.my_table".to_string()));
+    }
+
+    #[test]
+    fn test_parse_insert_overwrite_query() {
+        let query = "insert overwrite table test.my_table select id, name from test.another_table";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(1, table_names.len());
+        assert_eq!("test.another_table", table_names[0]);
+    }
+
+    #[test]
+    fn test_parse_insert_overwrite_query_1() {
+        let query = "-- set config test\n\
+                SET hive.exec.dynamic.partition.mode=nonstrict; -- hello comment select * from test.aaaa\n\
+                -- select * from test.bbbb\n\
+                \n\
+                with temp_a as (select * from test.table_1 where id=1)insert overwrite table test.my_table select id, name from test.another_table a join temp_a b on a.id=b.id";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(2, table_names.len());
+        assert!(table_names.contains(&"test.another_table".to_string()));
+        assert!(table_names.contains(&"test.table_1".to_string()));
+    }
+
+    #[test]
+    fn test_parse_subquery() {
+        let query = "SET hivevar:min_price=100;\n\
+                -- nothing to do: select * from test.bbbb\n\
+                \n\
+                select id, name from (select id, name from test.my_table) t where t.id > 10";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(1, table_names.len());
+        assert_eq!("test.my_table", table_names[0]);
+    }
+
+    #[test]
+    fn test_parse_join_query() {
+        let query =
+            "select t1.id, t2.name from test.table1 t1 join test.table2 t2 on t1.id = t2.id";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(2, table_names.len());
+        assert!(table_names.contains(&"test.table1".to_string()));
+        assert!(table_names.contains(&"test.table2".to_string()));
+    }
+
+    #[test]
+    fn test_parse_join_query_1() {
+        let query = "select t1.id, t2.name from test.table1 t1 join test.table2 t2 on t1.id = t2.id left join (select * from test.table3 a, test.table9 b where a.id=b.id ) t3 on t2.name =t3.name";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(4, table_names.len());
+        assert!(table_names.contains(&"test.table1".to_string()));
+        assert!(table_names.contains(&"test.table2".to_string()));
+        assert!(table_names.contains(&"test.table3".to_string()));
+        assert!(table_names.contains(&"test.table9".to_string()));
+    }
+
+    #[test]
+    fn test_parse_join_query_3() {
+        let query = "with temp_a as (select * from test.table5), temp_b as (select * from test.table6), temp_c as (select * from temp_a join temp_b on temp_a.id=temp_b.id)select t1.id, t2.name from test.table1 t1 join test.table2 t2 on t1.id = t2.id left join (select * from test.table3 a, test.table9 b where a.id=b.id ) t3 on t2.name =t3.name right join temp_c t4 on t1.id=t4.id";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(6, table_names.len());
+        assert!(table_names.contains(&"test.table1".to_string()));
+        assert!(table_names.contains(&"test.table2".to_string()));
+        assert!(table_names.contains(&"test.table3".to_string()));
+        assert!(table_names.contains(&"test.table9".to_string()));
+        assert!(table_names.contains(&"test.table5".to_string()));
+        assert!(table_names.contains(&"test.table6".to_string()));
+    }
+
+    #[test]
+    fn test_parse_exists_query() {
+        let query = "select id, name from test.my_table where exists (select 1 from test.another_table where another_table.id = my_table.id)";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(2, table_names.len());
+        assert!(table_names.contains(&"test.my_table".to_string()));
+        assert!(table_names.contains(&"test.another_table".to_string()));
+    }
+
+    #[test]
+    fn test_parse_in_query() {
+        let query =
+            "select id, name from test.my_table where id in (select id from test.filter_table)";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(2, table_names.len());
+        assert!(table_names.contains(&"test.my_table".to_string()));
+        assert!(table_names.contains(&"test.filter_table".to_string()));
+    }
+
+    #[test]
+    fn test_parse_group_by_query() {
+        let query = "select id, count(*) from test.my_table group by id";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(1, table_names.len());
+        assert!(table_names.contains(&"test.my_table".to_string()));
+    }
+
+    #[test]
+    fn test_parse_order_by_query() {
+        let query = "select id, name from test.my_table order by id desc";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(1, table_names.len());
+        assert!(table_names.contains(&"test.my_table".to_string()));
+    }
+
+    #[test]
+    fn test_parse_having_query() {
+        let query = "select id, count(*) as cnt from test.my_table group by id having cnt > 5";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(1, table_names.len());
+        assert!(table_names.contains(&"test.my_table".to_string()));
+    }
+
+    #[test]
+    fn test_parse_distinct_query() {
+        let query = "select distinct id from test.my_table";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(1, table_names.len());
+        assert!(table_names.contains(&"test.my_table".to_string()));
+    }
+
+    #[test]
+    fn test_parse_subquery_in_where() {
+        let query = "select id, name from test.my_table where id = (select max(id) from test.another_table)";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(2, table_names.len());
+        assert!(table_names.contains(&"test.my_table".to_string()));
+        assert!(table_names.contains(&"test.another_table".to_string()));
+    }
+
+    #[test]
+    fn test_parse_multiple_statements() {
+        let query = "insert overwrite table test.table1 select id, name from test.another_table1; insert overwrite table test.table2 select id, name from test.another_table2";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(2, table_names.len());
+        assert!(table_names.contains(&"test.another_table1".to_string()));
+        assert!(table_names.contains(&"test.another_table2".to_string()));
+    }
+    #[test]
+    fn test_use_with_insert_into() {
+        let query = "use test_db; insert into test.my_table values (1, 'John')";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(0, table_names.len());
+        // assert_eq!("test.my_table", table_names[0]);
+    }
+
+    #[test]
+    fn test_use_with_create_table_as_select() {
+        let query =
+            "use test_db; create table test.new_table as select id, name from test.my_table";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(1, table_names.len());
+        assert!(table_names.contains(&"test.my_table".to_string()));
+    }
+
+    #[test]
+    fn test_use_with_with_clause() {
+        let query = "use test_db; with cte_table as (select id, name from test.my_table) select * from cte_table";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(1, table_names.len());
+        assert!(table_names.contains(&"test.my_table".to_string()));
+    }
+
+    #[test]
+    fn test_use_with_insert_overwrite() {
+        let query = "use test_db; insert overwrite table test.my_table select id, name from test.another_table";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(1, table_names.len());
+        assert_eq!("test.another_table", table_names[0]);
+    }
+
+    #[test]
+    fn test_use_with_subquery() {
+        let query = "use test_db; select id, name from (select id, name from test.my_table) t where t.id > 10";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(1, table_names.len());
+        assert_eq!("test.my_table", table_names[0]);
+    }
+
+    #[test]
+    fn test_use_with_join_query() {
+        let query = "use test_db; select t1.id, t2.name from test.table1 t1 join test.table2 t2 on t1.id = t2.id";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(2, table_names.len());
+        assert!(table_names.contains(&"test.table1".to_string()));
+        assert!(table_names.contains(&"test.table2".to_string()));
+    }
+
+    #[test]
+    fn test_use_with_complex_join_query() {
+        let query = "use test_db; select t1.id, t2.name from test.table1 t1 join test.table2 t2 on t1.id = t2.id left join (select * from test.table3 a, test.table9 b where a.id=b.id ) t3 on t2.name =t3.name";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(4, table_names.len());
+        assert!(table_names.contains(&"test.table1".to_string()));
+        assert!(table_names.contains(&"test.table2".to_string()));
+        assert!(table_names.contains(&"test.table3".to_string()));
+        assert!(table_names.contains(&"test.table9".to_string()));
+    }
+
+    #[test]
+    fn test_use_with_exists_query() {
+        let query = "use test_db; select id, name from test.my_table where exists (select 1 from test.another_table where another_table.id = my_table.id)";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(2, table_names.len());
+        assert!(table_names.contains(&"test.my_table".to_string()));
+        assert!(table_names.contains(&"test.another_table".to_string()));
+    }
+
+    #[test]
+    fn test_use_with_in_query() {
+        let query = "use test_db; select id, name from test.my_table where id in (select id from test.filter_table)";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(2, table_names.len());
+        assert!(table_names.contains(&"test.my_table".to_string()));
+        assert!(table_names.contains(&"test.filter_table".to_string()));
+    }
+
+    #[test]
+    fn test_use_with_group_by_query() {
+        let query = "use test_db; select id, count(*) from test.my_table group by id";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(1, table_names.len());
+        assert!(table_names.contains(&"test.my_table".to_string()));
+    }
+
+    #[test]
+    fn test_use_with_order_by_query() {
+        let query = "use test_db; select id, name from test.my_table order by id desc";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(1, table_names.len());
+        assert!(table_names.contains(&"test.my_table".to_string()));
+    }
+
+    #[test]
+    fn test_use_with_having_query() {
+        let query =
+            "use test_db; select id, count(*) as cnt from test.my_table group by id having cnt > 5";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(1, table_names.len());
+        assert!(table_names.contains(&"test.my_table".to_string()));
+    }
+
+    #[test]
+    fn test_use_with_partitioned_table() {
+        let query = "use test_db; CREATE TABLE test.partitioned_table (id INT, name STRING) PARTITIONED BY (dt STRING)";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(0, table_names.len());
+    }
+
+    #[test]
+    fn test_use_with_insert_into_partition() {
+        let query = "use test_db; INSERT INTO TABLE test.partitioned_table PARTITION (dt='2023-05-01') SELECT id, name FROM test.source_table";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(1, table_names.len());
+        assert!(table_names.contains(&"test.source_table".to_string()));
+    }
+
+    #[test]
+    fn test_use_with_create_external_table() {
+        let query = "use test_db; CREATE EXTERNAL TABLE test.external_table (id INT, name STRING) STORED AS PARQUET LOCATION '/path/to/data'";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(0, table_names.len());
+    }
+
+    #[test]
+    fn test_use_with_bucketed_table() {
+        let query = r#"
+CREATE TABLE user_info_bucketed(user_id BIGINT, firstname STRING, lastname STRING)
+COMMENT 'A bucketed copy of user_info'
+PARTITIONED BY(ds STRING)
+CLUSTERED BY(user_id) INTO 256 BUCKETS;"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(0, table_names.len());
+    }
+
+    #[test]
+    fn test_use_with_window_function() {
+        let query = "use test_db; SELECT id, name, AVG(salary) OVER (PARTITION BY department ORDER BY salary) AS avg_salary FROM test.employee_table";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(1, table_names.len());
+        assert!(table_names.contains(&"test.employee_table".to_string()));
+    }
+
+    #[test]
+    fn test_use_with_lateral_view_explode() {
+        let query = "use test_db; SELECT t.id, t.name, item FROM test.table_with_array t LATERAL VIEW EXPLODE(t.items) itemTable AS item";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(1, table_names.len());
+        assert!(table_names.contains(&"test.table_with_array".to_string()));
+    }
+
+    #[test]
+    fn test_use_with_complex_union_query() {
+        let query = "SET hive.exec.parallel=true;\n\
+                     use test_db; SELECT id, name FROM test.table1 UNION ALL SELECT id, name FROM test.table2 UNION SELECT id, name FROM test.table3";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(3, table_names.len());
+        assert!(table_names.contains(&"test.table1".to_string()));
+        assert!(table_names.contains(&"test.table2".to_string()));
+        assert!(table_names.contains(&"test.table3".to_string()));
+    }
+
+    #[test]
+    fn test_use_with_union_in_cte() {
+        let query = "use test_db; WITH cte AS (SELECT id, name FROM test.table1 UNION ALL SELECT id, name FROM test.table2) SELECT * FROM cte";
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(2, table_names.len());
+        assert!(table_names.contains(&"test.table1".to_string()));
+        assert!(table_names.contains(&"test.table2".to_string()));
+    }
+
+    #[test]
+    fn test_multi_line_comment() {
+        let query = r#"/* This is a multi-line comment
+                       spanning multiple lines */
+                       SELECT * FROM test.table1;"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 1);
+        assert!(table_names.contains(&"test.table1".to_string()));
+    }
+
+    #[test]
+    fn test_comment_within_join_clause() {
+        let query = r#"SELECT t1.*, t2.* FROM test.table1 t1
+                       JOIN /* This is a comment within a JOIN clause */ test.table2 t2
+                       ON t1.id = t2.id;"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 2);
+        assert!(table_names.contains(&"test.table1".to_string()));
+        assert!(table_names.contains(&"test.table2".to_string()));
+    }
+
+    #[test]
+    fn test_comment_within_subquery() {
+        let query = r#"SELECT * FROM (
+                           SELECT id, name -- This is a comment within a subquery
+                           FROM test.table1
+                           WHERE id > 10
+                       ) subquery;"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 1);
+        assert!(table_names.contains(&"test.table1".to_string()));
+    }
+
+    #[test]
+    fn test_comment_within_cte() {
+        let query = r#"WITH cte AS (
+                           -- This is a comment within a CTE
+                           SELECT id, name FROM test.table1
+                       )
+                       SELECT * FROM cte;"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 1);
+        assert!(table_names.contains(&"test.table1".to_string()));
+    }
+
+    #[test]
+    fn test_comment_within_insert_statement() {
+        let query = r#"INSERT INTO test.table2
+                       -- This is a comment within an INSERT statement
+                       SELECT * FROM test.table1;"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 1);
+        assert!(table_names.contains(&"test.table1".to_string()));
+    }
+
+    #[test]
+    fn test_multiple_comments_in_complex_query() {
+        let query = r#"-- Initial comment
+                       WITH cte AS (
+                           SELECT id, name -- Comment in CTE
+                           FROM test.table1
+                       )
+                       SELECT c.*, t2.* -- Comment in main query
+                       FROM cte c
+                       JOIN /* Multi-line comment
+                               in JOIN clause */ test.table2 t2
+                       ON c.id = t2.id
+                       WHERE c.id > 10; -- Final comment"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 2);
+        assert!(table_names.contains(&"test.table1".to_string()));
+        assert!(table_names.contains(&"test.table2".to_string()));
+    }
+
+    #[test]
+    fn test_window_function_with_partition_and_order_by() {
+        let query = r#"-- Calculate running total of sales for each store
+                       USE sales_db;
+                       SELECT store_id, sale_date, sales_amount,
+                              SUM(sales_amount) OVER (PARTITION BY store_id ORDER BY sale_date) AS running_total
+                       FROM sales_table;"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 1);
+        assert!(table_names.contains(&"sales_db.sales_table".to_string()));
+    }
+
+    #[test]
+    fn test_window_function_with_rows_between() {
+        let query = r#"USE inventory_db;
+                       -- Calculate 7-day moving average of inventory levels
+                       SELECT product_id, `date`, inventory_level,
+                              AVG(inventory_level) OVER (
+                                  PARTITION BY product_id
+                                  ORDER BY `date`
+                                  ROWS BETWEEN 6 PRECEDING AND CURRENT ROW
+                              ) AS moving_average
+                       FROM inventory_table;"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 1);
+        assert!(table_names.contains(&"inventory_db.inventory_table".to_string()));
+    }
+
+    #[test]
+    fn test_multiple_window_functions_with_different_partitions() {
+        let query = r#"-- Calculate rank and dense rank within each department and overall
+                       USE hr_db;
+                       SELECT employee_id, department, salary,
+                              RANK() OVER (PARTITION BY department ORDER BY salary DESC) AS dept_rank,
+                              DENSE_RANK() OVER (ORDER BY salary DESC) AS overall_dense_rank
+                       FROM employee_table;"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 1);
+        assert!(table_names.contains(&"hr_db.employee_table".to_string()));
+    }
+
+    #[test]
+    fn test_window_function_in_subquery() {
+        let query = r#"USE sales_db;
+                       -- Find top 5 products by sales in each category
+                       SELECT * FROM (
+                           SELECT product_id, category, total_sales,
+                                  DENSE_RANK() OVER (PARTITION BY category ORDER BY total_sales DESC) AS sales_rank
+                           FROM (
+                               -- Calculate total sales for each product
+                               SELECT product_id, category, SUM(sales_amount) AS total_sales
+                               FROM sales_table
+                               GROUP BY product_id, category
+                           ) t
+                       ) t2
+                       WHERE sales_rank <= 5;"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 1);
+        assert!(table_names.contains(&"sales_db.sales_table".to_string()));
+    }
+
+    #[test]
+    fn test_window_function_with_join() {
+        let query = r#"-- Calculate employee's salary percentile within their department
+                       USE hr_db;
+                       SELECT e.employee_id, e.department, e.salary, d.dept_name,
+                              CUME_DIST() OVER (PARTITION BY e.department ORDER BY e.salary) AS salary_percentile
+                       FROM employee_table e
+                       JOIN test.department_table d ON e.department = d.dept_id;"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 2);
+        assert!(table_names.contains(&"hr_db.employee_table".to_string()));
+        assert!(table_names.contains(&"test.department_table".to_string()));
+    }
+
+    #[test]
+    fn test_multiple_uses_with_union() {
+        let query = r#"-- Select from table in db1
+                       USE db1;
+                       SELECT * FROM table1
+                       UNION ALL
+                       -- Select from table in db2
+                       SELECT * FROM db2.table2;"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 2);
+        assert!(table_names.contains(&"db1.table1".to_string()));
+        assert!(table_names.contains(&"db2.table2".to_string()));
+    }
+
+    #[test]
+    fn test_multiple_uses_with_join() {
+        let query = r#"-- Join tables from different databases
+                       USE db1;
+                       SELECT t1.*, t2.*
+                       FROM table1 t1
+                       JOIN db2.table2 t2 ON t1.id = t2.id;"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 2);
+        assert!(table_names.contains(&"db1.table1".to_string()));
+        assert!(table_names.contains(&"db2.table2".to_string()));
+    }
+
+    #[test]
+    fn test_multiple_uses_in_cte() {
+        let query = r#"-- CTE with tables from different databases
+                      USE db1;
+                       WITH cte1 AS (
+                           SELECT * FROM table1
+                       ),
+                       cte2 AS (
+                           SELECT * FROM db2.table2
+                       )
+                       SELECT * FROM cte1
+                       UNION ALL
+                       SELECT * FROM cte2;"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 2);
+        assert!(table_names.contains(&"db1.table1".to_string()));
+        assert!(table_names.contains(&"db2.table2".to_string()));
+    }
+
+    #[test]
+    fn test_comment_before_and_after_sql() {
+        let query = r#"-- This is a comment before the SQL
+                       SELECT * FROM test.table1;
+                       -- This is a comment after the SQL"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 1);
+        assert!(table_names.contains(&"test.table1".to_string()));
+    }
+
+    #[test]
+    fn test_comment_between_sql_statements() {
+        let query = r#"SELECT * FROM test.table1;
+                       -- This is a comment between SQL statements
+                       SELECT * FROM test.table2;"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 2);
+        assert!(table_names.contains(&"test.table1".to_string()));
+        assert!(table_names.contains(&"test.table2".to_string()));
+    }
+
+    #[test]
+    fn test_comment_within_sql() {
+        let query = r#"SELECT * FROM test.table1 -- This is an inline comment
+                       WHERE id > 10;"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 1);
+        assert!(table_names.contains(&"test.table1".to_string()));
+    }
+
+    #[test]
+    fn test_multiple_statements_with_comment() {
+        let query = r#"set tez.queue.name=root.test; -- set queue name 
+                       -- First, let's select from test.my_table
+                       select * from test.my_table;
+                       /* Now, let's switch to another database
+                       select * from 
+                       xhw.test where exists (select id from txs.good)  
+                       hello world */
+                       use test_db;
+                       -- Finally, let's select from test.another_table1
+                       SET hive.exec.dynamic.partition=true;
+                       SET hive.exec.dynamic.partition.mode=nonstrict;
+                       select * from another_table
+                       -- Finally, let's select from test.another_table1;
+                       SET mapreduce.job.name='My Sales Report';"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 2);
+        assert!(table_names.contains(&"test.my_table".to_string()));
+        assert!(table_names.contains(&"test_db.another_table".to_string()));
+    }
+
+    #[test]
+    fn test_multiple_uses_with_case_statement() {
+        let query = r#"SET hive.fetch.task.conversion=more;
+                       USE sales_db;
+                       SELECT s.product_id,
+                              CASE
+                                  WHEN p.category = 'Electronics' THEN 'Tech'
+                                  WHEN p.category = 'Clothing' THEN 'Apparel'
+                                  ELSE 'Other'
+                              END AS category_group,
+                              SUM(s.amount) AS total_sales
+                       FROM sales_table s
+                       JOIN product_db.product_table p ON s.product_id = p.id
+                       GROUP BY s.product_id, category_group;
+                       SET hive.exec.dynamic.partition=true;
+                       SET hive.exec.dynamic.partition.mode=nonstrict;
+                       USE db1;
+                       SELECT *
+                       FROM table1 t1
+                       WHERE EXISTS (
+                         -- Subquery from another database
+                         SELECT 1 FROM db2.table2 t2 WHERE t2.id = t1.id
+                       );
+                       use hello;"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 4);
+        assert!(table_names.contains(&"sales_db.sales_table".to_string()));
+        assert!(table_names.contains(&"product_db.product_table".to_string()));
+        assert!(table_names.contains(&"db1.table1".to_string()));
+        assert!(table_names.contains(&"db2.table2".to_string()));
+    }
+
+    #[test]
+    fn test_multiple_uses_with_cte() {
+        let query = r#"-- Define CTE in one database
+                       USE db1;
+                       WITH cte AS (
+                         SELECT * FROM table1
+                       )
+                       SELECT * FROM cte c
+                       JOIN db2.table2 t ON c.id = t.id;"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 2);
+        assert!(table_names.contains(&"db1.table1".to_string()));
+        assert!(table_names.contains(&"db2.table2".to_string()));
+    }
+
+    #[test]
+    fn test_multiple_uses_with_group_by_and_having() {
+        let query = r#"USE sales_db;
+                       SELECT product, SUM(amount) AS total_sales
+                       FROM sales_table
+                       GROUP BY product
+                       HAVING SUM(amount) > (
+                         SELECT AVG(quantity * price) FROM inventory_db.inventory_table
+                       );"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 2);
+        assert!(table_names.contains(&"sales_db.sales_table".to_string()));
+        assert!(table_names.contains(&"inventory_db.inventory_table".to_string()));
+    }
+
+    #[test]
+    fn test_multiple_uses_with_lateral_view() {
+        let query = r#"-- Select from table in db1
+                       USE db1;
+                       SELECT t.id, t.col1, lv.col2
+                       FROM table1 t
+                        -- Join with table from db2
+                       JOIN db2.table2 t2 ON t.id = t2.id
+                       LATERAL VIEW explode(split(t.col2, ',')) lv AS col2;"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 2);
+        assert!(table_names.contains(&"db1.table1".to_string()));
+        assert!(table_names.contains(&"db2.table2".to_string()));
+    }
+
+    #[test]
+    fn test_multiple_uses_with_subquery() {
+        let query = r#"USE db1;
+                       SELECT *
+                       FROM table1
+                       WHERE id IN (
+                         -- Subquery from another database
+                         SELECT id FROM db2.table2
+                       );"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 2);
+        assert!(table_names.contains(&"db1.table1".to_string()));
+        assert!(table_names.contains(&"db2.table2".to_string()));
+    }
+
+    #[test]
+    fn test_multiple_uses_with_union_and_join() {
+        let query = r#"-- Union tables from different databases
+                       USE db1;
+                       SELECT * FROM table1
+                       UNION ALL
+                       SELECT * FROM db2.table2
+                       -- Join with table from another database
+                       JOIN db3.table3 ON db1.table1.id = db3.table3.id;"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 3);
+        assert!(table_names.contains(&"db1.table1".to_string()));
+        assert!(table_names.contains(&"db2.table2".to_string()));
+        assert!(table_names.contains(&"db3.table3".to_string()));
+    }
+
+    #[test]
+    fn test_multiple_uses_with_window_function() {
+        let query = r#"-- Calculate running total sales
+                       USE sales_db;
+                       SELECT s.`date`, p.name, SUM(s.amount) OVER (ORDER BY s.`date`) AS running_total
+                       FROM sales_table s
+                       -- Join with product info from another database
+                       JOIN product_db.product_table p ON s.product_id = p.id;"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 2);
+        assert!(table_names.contains(&"sales_db.sales_table".to_string()));
+        assert!(table_names.contains(&"product_db.product_table".to_string()));
+    }
+
+    #[test]
+    fn test_parse_bucketed_table() {
+        let query = r#"CREATE TABLE test.bucketed_table (id INT, name STRING) CLUSTERED BY (id) INTO 4 BUCKETS"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 0);
+    }
+
+    #[test]
+    fn test_parse_complex_join_with_subqueries() {
+        let query = r#"SELECT a.id, b.name, c.value FROM 
+                       (SELECT id FROM test.table1 WHERE id > 100) a 
+                       JOIN test.table2 b ON a.id = b.id 
+                       LEFT JOIN (SELECT id, MAX(value) as value FROM test.table3 GROUP BY id) c ON a.id = c.id"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 3);
+        assert!(table_names.contains(&"test.table1".to_string()));
+        assert!(table_names.contains(&"test.table2".to_string()));
+        assert!(table_names.contains(&"test.table3".to_string()));
+    }
+
+    #[test]
+    fn test_parse_complex_union_query() {
+        let query = r#"SELECT id, name FROM test.table1 UNION ALL SELECT id, name FROM test.table2 UNION SELECT id, name FROM test.table3"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 3);
+        assert!(table_names.contains(&"test.table1".to_string()));
+        assert!(table_names.contains(&"test.table2".to_string()));
+        assert!(table_names.contains(&"test.table3".to_string()));
+    }
+
+    #[test]
+    fn test_parse_create_external_table() {
+        let query = r#"CREATE EXTERNAL TABLE test.external_table (id INT, name STRING) STORED AS PARQUET LOCATION '/path/to/data'"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 0);
+    }
+
+    #[test]
+    fn test_parse_create_view_query() {
+        let query =
+            r#"CREATE VIEW test.my_view AS SELECT id, name FROM test.base_table WHERE id > 100"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 1);
+        assert!(table_names.contains(&"test.base_table".to_string()));
+    }
+
+    #[test]
+    fn test_parse_insert_into_partition() {
+        let query = r#"INSERT INTO TABLE test.partitioned_table PARTITION (dt='2023-05-01') SELECT id, name FROM test.source_table"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 1);
+        assert!(table_names.contains(&"test.source_table".to_string()));
+    }
+
+    #[test]
+    fn test_parse_insert_overwrite_directory() {
+        let query =
+            r#"INSERT OVERWRITE DIRECTORY '/output/path' SELECT id, name FROM test.source_table"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 1);
+        assert!(table_names.contains(&"test.source_table".to_string()));
+    }
+
+    #[test]
+    fn test_parse_lateral_view_explode() {
+        let query = r#"SELECT t.id, t.name, item FROM test.table_with_array t LATERAL VIEW EXPLODE(t.items) itemTable AS item"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 1);
+        assert!(table_names.contains(&"test.table_with_array".to_string()));
+    }
+
+    #[test]
+    fn test_parse_partitioned_table() {
+        let query = r#"CREATE TABLE test.partitioned_table (id INT, name STRING) PARTITIONED BY (dt STRING)"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 0);
+    }
+
+    #[test]
+    fn test_parse_window_function() {
+        let query = r#"SELECT id, name, AVG(salary) OVER (PARTITION BY department ORDER BY salary) AS avg_salary FROM test.employee_table"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 1);
+        assert!(table_names.contains(&"test.employee_table".to_string()));
+    }
+
+    #[test]
+    fn test_select_with_comment() {
+        let query = r#"select id, name from test.my_table -- Select id, name from xxx.test \nwhere id > 10 -- Only records with id greater than 10"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 1);
+        assert!(table_names.contains(&"test.my_table".to_string()));
+    }
+
+    #[test]
+    fn test_use_after_select_query() {
+        let query = r#"select id, name from test.my_table where id > 10; use test_db"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 1);
+        assert_eq!(table_names[0], "test.my_table".to_string());
+    }
+
+    #[test]
+    fn test_use_before_select_query() {
+        let query = r#"use test_db; select id, name from test.my_table where id > 10"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 1);
+        assert_eq!(table_names[0], "test.my_table".to_string());
+    }
+
+    #[test]
+    fn test_use_with_complex_join_with_subqueries() {
+        let query = r#"SET hive.exec.compress.output=true;
+                       SET mapreduce.output.fileoutputformat.compress.codec=org.apache.hadoop.io.compress.GzipCodec;
+                       SELECT id, name FROM test.table_1 UNION ALL SELECT id, name FROM table_2 UNION SELECT id, name FROM test.table_3;
+                       use test_db; SELECT a.id, b.name, c.value FROM 
+                       (SELECT id FROM test.table1 WHERE id > 100) a 
+                       JOIN table2 b ON a.id = b.id 
+                       LEFT JOIN (SELECT id, MAX(value) as value FROM test.table3 GROUP BY id) c ON a.id = c.id"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 6);
+        assert!(table_names.contains(&"test.table1".to_string()));
+        assert!(table_names.contains(&"test.table_1".to_string()));
+        assert!(table_names.contains(&"default.table_2".to_string()));
+        assert!(table_names.contains(&"test_db.table2".to_string()));
+        assert!(table_names.contains(&"test.table3".to_string()));
+        assert!(table_names.contains(&"test.table_3".to_string()));
+    }
+
+    #[test]
+    fn test_use_with_create_table() {
+        let query = r#"-- ok to test: select 1 from tst.xxx
+                       SET hive.enforce.bucketing=true; --- enforce buctket to true 
+                       CREATE TABLE users_bucketed (
+                         user_id INT, -- to comment 
+                         name STRING comment 'ddddd',
+                         age INT
+                       )
+                       CLUSTERED BY (user_id) INTO 4 BUCKETS;"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 0);
+    }
+
+    #[test]
+    fn test_use_with_create_view_query() {
+        let query = r#"use test_db; CREATE VIEW test.my_view AS SELECT id, name FROM test.base_table WHERE id > 100"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 1);
+        assert!(table_names.contains(&"test.base_table".to_string()));
+    }
+
+    #[test]
+    fn test_use_with_insert_overwrite_directory() {
+        let query = r#"-- test set queue name and mutil line comment  select * from test.111111 
+                       set tez.queue.name=root.test; 
+                       use test_db; INSERT OVERWRITE DIRECTORY '/output/path' SELECT id, name FROM test.source_table"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 1);
+        assert!(table_names.contains(&"test.source_table".to_string()));
+    }
+
+    #[test]
+    fn test_parse_create_table_with_check_constraint() {
+        let query = r#"CREATE TABLE test.checked_table (
+                         id INT DEFAULT 0,
+                         name STRING,
+                         CHECK (id >= 0)
+                       )"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 0);
+    }
+
+    #[test]
+    fn test_sources_for_statement() {
+        let query = r#"select * from test.table1;
+                       use test_db;
+                       select * from table2 t2 join test.table3 t3 on t2.id = t3.id;
+                       select * from test.table4"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        assert_eq!(processor.get_statements().len(), 3);
+        assert_eq!(
+            processor.sources_for_statement(1),
+            Some(vec![
+                "test_db.table2".to_string(),
+                "test.table3".to_string()
+            ])
+        );
+        assert_eq!(processor.sources_for_statement(3), None);
+    }
+
+    #[test]
+    fn test_parse_long_union_chain() {
+        let query = (0..100)
+            .map(|i| format!("select id from test.table_{}", i))
+            .collect::<Vec<_>>()
+            .join(" union all ");
+        let mut processor = HiveSqlParser::new();
+        processor.parse(&query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 100);
+        for (i, name) in table_names.iter().enumerate() {
+            assert_eq!(name, &format!("test.table_{}", i));
+        }
+    }
+
+    #[test]
+    fn test_parse_with_clause_over_union_without_from() {
+        let query = r#"with c as (select * from test.a) select 1 union all select * from c"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names, vec!["test.a".to_string()]);
+    }
+
+    #[test]
+    fn test_parse_quoted_reserved_word_database() {
+        let query = r#"use test_db; select * from `default`.t"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names, vec!["default.t".to_string()]);
+    }
+
+    #[test]
+    fn test_parse_with_batch_separators() {
+        let query = r#"select * from test.table1
+                       GO
+                       select * from test.table2
+                       go"#;
+        let mut processor = HiveSqlParser::new().with_batch_separators(&["go"]);
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 2);
+        assert!(table_names.contains(&"test.table1".to_string()));
+        assert!(table_names.contains(&"test.table2".to_string()));
+    }
+
+    #[test]
+    fn test_parse_at_time_zone_subquery() {
+        let query = r#"select id from test.events
+                       where event_time at time zone (select tz from test.cfg) > '2024-01-01'"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 2);
+        assert!(table_names.contains(&"test.events".to_string()));
+        assert!(table_names.contains(&"test.cfg".to_string()));
+    }
+
+    #[test]
+    fn test_parse_create_temporary_table() {
+        let query = r#"create temporary table test.tmp as select * from test.s;
+                       create table test.persistent as select * from test.tmp"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        assert_eq!(
+            processor.get_temporary_tables(),
+            vec!["test.tmp".to_string()]
+        );
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 2);
+        assert!(table_names.contains(&"test.s".to_string()));
+        assert!(table_names.contains(&"test.tmp".to_string()));
+    }
+
+    #[test]
+    fn test_parse_insert_overwrite_directory_with_row_format() {
+        let query = r#"INSERT OVERWRITE LOCAL DIRECTORY '/output/path'
+                       ROW FORMAT DELIMITED FIELDS TERMINATED BY ',' LINES TERMINATED BY '\n'
+                       STORED AS TEXTFILE
+                       SELECT id, name FROM test.source_table;
+                       INSERT OVERWRITE DIRECTORY '/output/serde'
+                       ROW FORMAT SERDE 'org.apache.hadoop.hive.serde2.OpenCSVSerde'
+                       SELECT id FROM test.another_table"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 2);
+        assert!(table_names.contains(&"test.source_table".to_string()));
+        assert!(table_names.contains(&"test.another_table".to_string()));
+    }
+
+    #[test]
+    fn test_get_grouped_by_database() {
+        let query = r#"use a; insert overwrite table a.t select * from a.s, b.r"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let grouped = processor.get_grouped();
+        assert_eq!(grouped.len(), 2);
+        assert_eq!(grouped["a"], (vec!["s".to_string()], vec!["t".to_string()]));
+        assert_eq!(grouped["b"], (vec!["r".to_string()], vec![]));
+    }
+
+    #[test]
+    fn test_commented_set_before_select_from_set_schema() {
+        let query = r#"-- set hive.foo=bar;
+                       --set x=1
+                       select * from set_schema.t"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names, vec!["set_schema.t".to_string()]);
+    }
+
+    #[test]
+    fn test_outer_cte_referenced_in_where_subquery() {
+        let query = r#"with c as (select * from test.a)
+                       select * from test.b where id in (select id from c)"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names.len(), 2);
+        assert!(table_names.contains(&"test.a".to_string()));
+        assert!(table_names.contains(&"test.b".to_string()));
+    }
+
+    #[test]
+    fn test_parse_once() {
+        let query = "use test_db; select * from table1 t1 join test.table2 t2 on t1.id = t2.id";
+        let table_names = HiveSqlParser::parse_once(query).unwrap();
+        assert_eq!(
+            table_names,
+            vec!["test_db.table1".to_string(), "test.table2".to_string()]
+        );
+        assert!(HiveSqlParser::parse_once("select from where").is_err());
     }
 
-    pub fn get_table_names(&self) -> Vec<String> {
-        self.all_table_names.clone()
+    #[test]
+    fn test_empty_object_name_is_skipped() {
+        let mut processor = HiveSqlParser::new();
+        processor.add_valid_table_name(&ObjectName(vec![]));
+        processor.parse("select * from test.my_table").unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(table_names, vec!["test.my_table".to_string()]);
+        assert!(!table_names.iter().any(|name| name.ends_with('.')));
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
 
     #[test]
-    fn test_use_between_queries() {
-        let query =
-            "select id, name from test.my_table where id > 10; select * from test.another_table";
+    fn test_parse_collate_subquery() {
+        let query = r#"select * from test.s where (select v from test.t) collate utf8 = name;
+                       select * from test.s where name collate utf8 = (select v from test.t2)"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(2, table_names.len());
-        assert!(table_names.contains(&"test.my_table".to_string()));
-        assert!(table_names.contains(&"test.another_table".to_string()));
+        assert_eq!(table_names.len(), 4);
+        assert!(table_names.contains(&"test.t".to_string()));
+        assert!(table_names.contains(&"test.t2".to_string()));
     }
 
     #[test]
-    fn test_parse_create_table() {
-        let query = "create table if not exists test.my_table(id int, name string)";
+    fn test_contains_ddl() {
+        let mut processor = HiveSqlParser::new();
+        processor
+            .parse("select * from test.a; create table test.b (id int); select * from test.c")
+            .unwrap();
+        assert!(processor.contains_ddl());
+
+        let mut processor = HiveSqlParser::new();
+        processor
+            .parse("select * from test.a; insert overwrite table test.b select * from test.c")
+            .unwrap();
+        assert!(!processor.contains_ddl());
+    }
+
+    #[test]
+    fn test_parse_four_way_chained_join() {
+        let query = r#"select a.id from test.a a
+                       join test.b b on a.id = b.id
+                       left join test.c c on b.id = c.id
+                       join test.d d on c.id = d.id"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(0, table_names.len());
+        assert_eq!(
+            table_names,
+            vec![
+                "test.a".to_string(),
+                "test.b".to_string(),
+                "test.c".to_string(),
+                "test.d".to_string()
+            ]
+        );
     }
 
     #[test]
-    fn test_parse_select_query() {
-        let query = "select id, name from test.my_table where id > 10";
+    fn test_parse_msck_repair_table() {
+        let query = r#"use test_db; msck repair table test.t; MSCK REPAIR TABLE t2"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(1, table_names.len());
-        assert_eq!("test.my_table", table_names[0]);
+        assert_eq!(
+            table_names,
+            vec!["test.t".to_string(), "test_db.t2".to_string()]
+        );
     }
 
     #[test]
-    fn test_parse_insert_query() {
-        let query = "insert into test.my_table values (1, 'John')";
+    fn test_parse_refresh_table() {
+        let query = r#"refresh test.t; use test_db; REFRESH TABLE `t2`"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(0, table_names.len());
-        // Uncomment if insert queries should be handled
-        // assert_eq!("test.my_table", table_names[0]);
+        assert_eq!(
+            table_names,
+            vec!["test.t".to_string(), "test_db.t2".to_string()]
+        );
+        assert!(HiveSqlParser::new().parse("refresh test.t extra").is_err());
     }
 
     #[test]
-    fn test_parse_create_table_as_select() {
-        let query = "create table test.new_table as select id, name from test.my_table";
+    fn test_strict_mode_unqualified_without_database() {
+        let mut processor = HiveSqlParser::new().with_strict_mode(true);
+        let err = processor.parse("select * from my_table").unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ParseError>(),
+            Some(&ParseError::UnqualifiedWithoutDatabase {
+                table: "my_table".to_string()
+            })
+        );
+
+        let mut processor = HiveSqlParser::new().with_strict_mode(true);
+        processor
+            .parse("use test_db; with c as (select * from test.a) select * from my_table join c")
+            .unwrap();
+        let table_names = processor.get_table_names();
+        assert_eq!(
+            table_names,
+            vec!["test.a".to_string(), "test_db.my_table".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_parse_top_level_values() {
+        let mut processor = HiveSqlParser::new();
+        processor.parse("values ((select x from test.t))").unwrap();
+        assert_eq!(processor.get_table_names(), vec!["test.t".to_string()]);
+
+        let mut processor = HiveSqlParser::new();
+        processor.parse("values (1), (2)").unwrap();
+        assert!(processor.get_table_names().is_empty());
+    }
+
+    #[test]
+    fn test_parse_join_against_union_subquery() {
+        let query = r#"select * from test.a a
+                       join (select * from test.b union all select * from test.c) x
+                       on a.id = x.id"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(1, table_names.len());
-        assert!(table_names.contains(&"test.my_table".to_string()));
+        assert_eq!(table_names.len(), 3);
+        assert!(table_names.contains(&"test.a".to_string()));
+        assert!(table_names.contains(&"test.b".to_string()));
+        assert!(table_names.contains(&"test.c".to_string()));
     }
 
     #[test]
-    fn test_parse_with_clause() {
-        let query =
-            "with cte_table as (select id, name from test.my_table) select * from cte_table";
+    fn test_references_table() {
+        let mut processor = HiveSqlParser::new();
+        processor.parse("select * from test.my_table").unwrap();
+        assert!(processor.references_table("TEST.MY_TABLE"));
+        assert!(processor.references_table("`test`.`my_table`"));
+        assert!(!processor.references_table("test.other_table"));
+    }
+
+    #[test]
+    fn test_parse_create_table_as_with_clause() {
+        let query = r#"create table test.t as with c as (select * from test.base) select * from c"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(1, table_names.len());
-        assert!(table_names.contains(&"test.my_table".to_string()));
+        assert_eq!(table_names, vec!["test.base".to_string()]);
     }
 
     #[test]
-    fn test_parse_insert_overwrite_query() {
-        let query = "insert overwrite table test.my_table select id, name from test.another_table";
+    fn test_parse_delete_using() {
+        let query = r#"delete from test.t using test.s where t.id = s.id"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        assert_eq!(processor.get_table_names(), vec!["test.s".to_string()]);
+        let grouped = processor.get_grouped();
+        assert_eq!(
+            grouped["test"],
+            (vec!["s".to_string()], vec!["t".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_get_max_depth() {
+        // 最外层查询为第 1 层，两层派生表嵌套后最大深度为 3
+        let query = r#"select * from (select * from (select id from test.t) a) b;
+                       select * from test.s where id in (select id from test.u)"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        assert_eq!(processor.get_max_depth(), 3);
+        assert_eq!(processor.get_table_names().len(), 3);
+    }
+
+    #[test]
+    fn test_set_with_quoted_semicolon() {
+        let query = r#"set mapreduce.job.name='Report; Daily';
+                       select * from test.t"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        assert_eq!(processor.get_table_names(), vec!["test.t".to_string()]);
+
+        let mut processor = HiveSqlParser::new();
+        processor
+            .parse("set mapreduce.job.name='Report; Daily'")
+            .unwrap();
+        assert!(processor.get_table_names().is_empty());
+    }
+
+    #[test]
+    fn test_parse_having_subquery_with_window_function() {
+        let query = r#"select a from test.t group by a
+                       having a > (select max(b) over () from test.cfg)"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(1, table_names.len());
-        assert_eq!("test.another_table", table_names[0]);
+        assert_eq!(
+            table_names,
+            vec!["test.t".to_string(), "test.cfg".to_string()]
+        );
     }
 
     #[test]
-    fn test_parse_insert_overwrite_query_1() {
-        let query = "-- set config test\n\
-                SET hive.exec.dynamic.partition.mode=nonstrict; -- hello comment select * from test.aaaa\n\
-                -- select * from test.bbbb\n\
-                \n\
-                with temp_a as (select * from test.table_1 where id=1)insert overwrite table test.my_table select id, name from test.another_table a join temp_a b on a.id=b.id";
+    fn test_parse_bucketed_table_without_bucket_regex() {
+        let query = r#"CREATE TABLE user_info_bucketed(user_id BIGINT, firstname STRING)
+                       PARTITIONED BY(ds STRING)
+                       CLUSTERED BY(user_id) SORTED BY (firstname) INTO 256 BUCKETS
+                       STORED AS ORC;
+                       select 'clustered by (x) into 2 buckets' as note from test.t"#;
+        let mut processor = HiveSqlParser::new().with_bucket_regex(false);
+        processor.parse(query).unwrap();
+        let statements = processor.get_statements();
+        assert_eq!(statements.len(), 2);
+        assert!(statements[0].source_tables.is_empty());
+        assert!(statements[1]
+            .sql
+            .contains("'clustered by (x) into 2 buckets'"));
+        assert_eq!(processor.get_table_names(), vec!["test.t".to_string()]);
+    }
+
+    #[test]
+    fn test_parse_exists_in_or_chain() {
+        let query = r#"select * from test.main
+                       where exists (select 1 from test.a) or exists (select 1 from test.b)"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(2, table_names.len());
-        assert!(table_names.contains(&"test.another_table".to_string()));
-        assert!(table_names.contains(&"test.table_1".to_string()));
+        assert_eq!(table_names.len(), 3);
+        assert!(table_names.contains(&"test.main".to_string()));
+        assert!(table_names.contains(&"test.a".to_string()));
+        assert!(table_names.contains(&"test.b".to_string()));
     }
 
     #[test]
-    fn test_parse_subquery() {
-        let query = "SET hivevar:min_price=100;\n\
-                -- nothing to do: select * from test.bbbb\n\
-                \n\
-                select id, name from (select id, name from test.my_table) t where t.id > 10";
+    fn test_parse_projection_subquery_with_join() {
+        let query = r#"select (select count(*) from test.a join test.b on a.id = b.id) as c"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(1, table_names.len());
-        assert_eq!("test.my_table", table_names[0]);
+        assert_eq!(
+            table_names,
+            vec!["test.a".to_string(), "test.b".to_string()]
+        );
     }
 
     #[test]
-    fn test_parse_join_query() {
+    fn test_parse_name_with_empty_component() {
+        assert!(HiveSqlParser::new().parse("select * from test..t").is_err());
+
+        let mut processor = HiveSqlParser::new();
+        processor.parse("select * from test.``.t").unwrap();
+        assert_eq!(processor.get_table_names(), vec!["test.t".to_string()]);
+    }
+
+    #[test]
+    fn test_parse_create_table_as_select_with_lateral_view() {
         let query =
-            "select t1.id, t2.name from test.table1 t1 join test.table2 t2 on t1.id = t2.id";
+            r#"create table test.t as select x, e from test.s lateral view explode(arr) v as e"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
-        let table_names = processor.get_table_names();
-        assert_eq!(2, table_names.len());
-        assert!(table_names.contains(&"test.table1".to_string()));
-        assert!(table_names.contains(&"test.table2".to_string()));
+        assert_eq!(processor.get_table_names(), vec!["test.s".to_string()]);
     }
 
     #[test]
-    fn test_parse_join_query_1() {
-        let query = "select t1.id, t2.name from test.table1 t1 join test.table2 t2 on t1.id = t2.id left join (select * from test.table3 a, test.table9 b where a.id=b.id ) t3 on t2.name =t3.name";
+    fn test_include_ctes() {
+        let query = r#"with c as (select * from test.a) select * from c"#;
+        let mut processor = HiveSqlParser::new().with_include_ctes(true);
+        processor.parse(query).unwrap();
+        assert_eq!(
+            processor.get_table_names(),
+            vec!["test.a".to_string(), "c".to_string()]
+        );
+        assert_eq!(
+            processor.get_table_usages(),
+            vec![
+                ("test.a".to_string(), TableUsage::Read),
+                ("c".to_string(), TableUsage::Cte)
+            ]
+        );
+
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
-        let table_names = processor.get_table_names();
-        assert_eq!(4, table_names.len());
-        assert!(table_names.contains(&"test.table1".to_string()));
-        assert!(table_names.contains(&"test.table2".to_string()));
-        assert!(table_names.contains(&"test.table3".to_string()));
-        assert!(table_names.contains(&"test.table9".to_string()));
+        assert_eq!(processor.get_table_names(), vec!["test.a".to_string()]);
     }
 
     #[test]
-    fn test_parse_join_query_3() {
-        let query = "with temp_a as (select * from test.table5), temp_b as (select * from test.table6), temp_c as (select * from temp_a join temp_b on temp_a.id=temp_b.id)select t1.id, t2.name from test.table1 t1 join test.table2 t2 on t1.id = t2.id left join (select * from test.table3 a, test.table9 b where a.id=b.id ) t3 on t2.name =t3.name right join temp_c t4 on t1.id=t4.id";
+    fn test_get_table_usages() {
+        let query = r#"insert overwrite table test.t select * from test.s"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
-        let table_names = processor.get_table_names();
-        assert_eq!(6, table_names.len());
-        assert!(table_names.contains(&"test.table1".to_string()));
-        assert!(table_names.contains(&"test.table2".to_string()));
-        assert!(table_names.contains(&"test.table3".to_string()));
-        assert!(table_names.contains(&"test.table9".to_string()));
-        assert!(table_names.contains(&"test.table5".to_string()));
-        assert!(table_names.contains(&"test.table6".to_string()));
+        assert_eq!(
+            processor.get_table_usages(),
+            vec![
+                ("test.s".to_string(), TableUsage::Read),
+                ("test.t".to_string(), TableUsage::Write)
+            ]
+        );
     }
 
     #[test]
-    fn test_parse_exists_query() {
-        let query = "select id, name from test.my_table where exists (select 1 from test.another_table where another_table.id = my_table.id)";
+    fn test_create_database() {
+        let mut processor = HiveSqlParser::new();
+        processor.parse("create database foo").unwrap();
+        assert_eq!(processor.get_created_databases(), vec!["foo".to_string()]);
+        assert!(processor.get_table_names().is_empty());
+    }
+
+    #[test]
+    fn test_create_schema_if_not_exists() {
+        let mut processor = HiveSqlParser::new();
+        processor.parse("create schema if not exists bar").unwrap();
+        assert_eq!(processor.get_created_databases(), vec!["bar".to_string()]);
+        assert!(processor.contains_ddl());
+    }
+
+    #[test]
+    fn test_derived_table_without_alias() {
+        let query = r#"select * from (select * from test.t)"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(2, table_names.len());
-        assert!(table_names.contains(&"test.my_table".to_string()));
-        assert!(table_names.contains(&"test.another_table".to_string()));
+        assert_eq!(table_names, vec!["test.t".to_string()]);
     }
 
     #[test]
-    fn test_parse_in_query() {
-        let query =
-            "select id, name from test.my_table where id in (select id from test.filter_table)";
+    fn test_insert_select_with_cte_and_union() {
+        let query = r#"insert into test.t with c as (select * from test.a) select * from c union all select * from test.b"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(2, table_names.len());
-        assert!(table_names.contains(&"test.my_table".to_string()));
-        assert!(table_names.contains(&"test.filter_table".to_string()));
+        assert_eq!(
+            table_names,
+            vec!["test.a".to_string(), "test.b".to_string()]
+        );
+        assert_eq!(
+            processor.get_table_usages(),
+            vec![
+                ("test.a".to_string(), TableUsage::Read),
+                ("test.b".to_string(), TableUsage::Read),
+                ("test.t".to_string(), TableUsage::Write)
+            ]
+        );
     }
 
     #[test]
-    fn test_parse_group_by_query() {
-        let query = "select id, count(*) from test.my_table group by id";
+    fn test_strict_mode_unsupported_statement() {
+        let mut processor = HiveSqlParser::new().with_reject_unsupported(true);
+        let err = processor.parse("show tables").unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ParseError>(),
+            Some(&ParseError::UnsupportedStatement {
+                kind: "ShowTables".to_string()
+            })
+        );
+        assert!(err.to_string().contains("ShowTables"));
+    }
+
+    #[test]
+    fn test_backtick_table_name_with_hyphen() {
+        let query = r#"select * from test.`my-table`"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(1, table_names.len());
-        assert!(table_names.contains(&"test.my_table".to_string()));
+        assert_eq!(table_names, vec!["test.my-table".to_string()]);
     }
 
     #[test]
-    fn test_parse_order_by_query() {
-        let query = "select id, name from test.my_table order by id desc";
+    fn test_tables_topo_order() {
+        let query = r#"create table t1 as select * from s; create table t2 as select * from t1"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
-        let table_names = processor.get_table_names();
-        assert_eq!(1, table_names.len());
-        assert!(table_names.contains(&"test.my_table".to_string()));
+        let (order, cycles) = processor.get_tables_topo_order();
+        assert_eq!(
+            order,
+            vec![
+                "default.s".to_string(),
+                "default.t1".to_string(),
+                "default.t2".to_string()
+            ]
+        );
+        assert!(cycles.is_empty());
     }
 
     #[test]
-    fn test_parse_having_query() {
-        let query = "select id, count(*) as cnt from test.my_table group by id having cnt > 5";
+    fn test_tables_topo_order_self_rewrite() {
+        let query = r#"insert overwrite table test.t select * from test.t where id > 0"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
-        let table_names = processor.get_table_names();
-        assert_eq!(1, table_names.len());
-        assert!(table_names.contains(&"test.my_table".to_string()));
+        let (order, cycles) = processor.get_tables_topo_order();
+        assert_eq!(order, vec!["test.t".to_string()]);
+        assert_eq!(cycles, vec!["test.t".to_string()]);
     }
 
     #[test]
-    fn test_parse_distinct_query() {
-        let query = "select distinct id from test.my_table";
+    fn test_cte_with_insert_body() {
+        let query = r#"with c as (insert into test.x select * from test.a) select * from c"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(1, table_names.len());
-        assert!(table_names.contains(&"test.my_table".to_string()));
+        assert_eq!(table_names, vec!["test.a".to_string()]);
+        assert_eq!(
+            processor.get_table_usages(),
+            vec![
+                ("test.a".to_string(), TableUsage::Read),
+                ("test.x".to_string(), TableUsage::Write)
+            ]
+        );
     }
 
     #[test]
-    fn test_parse_subquery_in_where() {
-        let query = "select id, name from test.my_table where id = (select max(id) from test.another_table)";
+    fn test_is_distinct_from_subquery() {
+        let query = r#"select * from test.s where a is distinct from (select x from test.t)"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(2, table_names.len());
-        assert!(table_names.contains(&"test.my_table".to_string()));
-        assert!(table_names.contains(&"test.another_table".to_string()));
+        assert_eq!(
+            table_names,
+            vec!["test.s".to_string(), "test.t".to_string()]
+        );
     }
 
     #[test]
-    fn test_parse_multiple_statements() {
-        let query = "insert overwrite table test.table1 select id, name from test.another_table1; insert overwrite table test.table2 select id, name from test.another_table2";
+    fn test_first_error() {
+        let mut processor = HiveSqlParser::new().with_reject_unsupported(true);
+        assert!(processor.first_error().is_none());
+        assert!(processor.parse("show tables").is_err());
+        assert!(processor.parse("show databases").is_err());
+        assert_eq!(processor.get_errors().len(), 2);
+        assert_eq!(
+            processor.first_error(),
+            Some(&ParseError::UnsupportedStatement {
+                kind: "ShowTables".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_mixed_case_use() {
+        let query = r#"USE Test_DB; select * from t"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(2, table_names.len());
-        assert!(table_names.contains(&"test.another_table1".to_string()));
-        assert!(table_names.contains(&"test.another_table2".to_string()));
+        assert_eq!(table_names, vec!["test_db.t".to_string()]);
+        assert!(HiveSqlParser::starts_with_keyword("UsE\tdb", "use"));
+        assert!(!HiveSqlParser::starts_with_keyword("user_table", "use"));
     }
+
     #[test]
-    fn test_use_with_insert_into() {
-        let query = "use test_db; insert into test.my_table values (1, 'John')";
+    fn test_tablesample_bucket() {
+        let query = r#"select * from test.t tablesample(bucket 1 out of 4 on id) s join test.u tablesample(bucket 1 out of 8 on rand()) on s.id = u.id"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(0, table_names.len());
-        // assert_eq!("test.my_table", table_names[0]);
+        assert_eq!(
+            table_names,
+            vec!["test.t".to_string(), "test.u".to_string()]
+        );
     }
 
     #[test]
-    fn test_use_with_create_table_as_select() {
-        let query =
-            "use test_db; create table test.new_table as select id, name from test.my_table";
+    fn test_preprocessing_diff_records_comment_removal() {
+        let query = "select * from test.a; -- select * from test.b\nselect * from test.c";
+        let mut processor = HiveSqlParser::new().with_diagnostics(true);
+        processor.parse(query).unwrap();
+        let diff = processor.get_preprocessing_diff();
+        assert_eq!(diff.len(), 1);
+        assert_eq!(diff[0].statement_index, 1);
+        assert_eq!(diff[0].step, "comment");
+        assert!(diff[0].before.contains("test.b"));
+        assert!(!diff[0].after.contains("test.b"));
+    }
+
+    #[test]
+    fn test_create_view_referencing_view() {
+        let query = r#"create view test.v1 as select * from test.v2"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
+        assert_eq!(processor.get_table_names(), vec!["test.v2".to_string()]);
+        assert_eq!(
+            processor.sources_for_statement(0),
+            Some(vec!["test.v2".to_string()])
+        );
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn test_parse_gzip_file() {
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+        use std::io::Write;
+
+        let path = std::env::temp_dir().join(format!("parse_hive_{}.hql.gz", std::process::id()));
+        let mut encoder = GzEncoder::new(fs::File::create(&path).unwrap(), Compression::default());
+        encoder
+            .write_all(b"use test;\nselect * from a;\ninsert into b select * from test2.c;\n")
+            .unwrap();
+        encoder.finish().unwrap();
+
+        let mut processor = HiveSqlParser::new();
+        processor.parse_gzip_file(&path).unwrap();
+        fs::remove_file(&path).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(1, table_names.len());
-        assert!(table_names.contains(&"test.my_table".to_string()));
+        assert_eq!(
+            table_names,
+            vec!["test.a".to_string(), "test2.c".to_string()]
+        );
     }
 
     #[test]
-    fn test_use_with_with_clause() {
-        let query = "use test_db; with cte_table as (select id, name from test.my_table) select * from cte_table";
+    fn test_case_with_subqueries() {
+        let query = r#"select case (select k from test.key) when 1 then (select v from test.a) else (select w from test.b) end from test.t"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(1, table_names.len());
-        assert!(table_names.contains(&"test.my_table".to_string()));
+        assert_eq!(4, table_names.len());
+        for name in ["test.key", "test.a", "test.b", "test.t"] {
+            assert!(table_names.contains(&name.to_string()));
+        }
     }
 
     #[test]
-    fn test_use_with_insert_overwrite() {
-        let query = "use test_db; insert overwrite table test.my_table select id, name from test.another_table";
+    fn test_comment_on_table() {
+        let query = r#"comment on table test.t is 'desc'; comment on column test.s.id is 'key'"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(1, table_names.len());
-        assert_eq!("test.another_table", table_names[0]);
+        assert_eq!(
+            table_names,
+            vec!["test.t".to_string(), "test.s".to_string()]
+        );
     }
 
     #[test]
-    fn test_use_with_subquery() {
-        let query = "use test_db; select id, name from (select id, name from test.my_table) t where t.id > 10";
+    fn test_target_table_names() {
+        let query = r#"insert into test.my_table select * from test.a;
+        create table test.b as select * from test.c;
+        insert overwrite directory '/tmp/out' select * from test.d"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
-        let table_names = processor.get_table_names();
-        assert_eq!(1, table_names.len());
-        assert_eq!("test.my_table", table_names[0]);
+        assert_eq!(
+            processor.get_table_names(),
+            vec![
+                "test.a".to_string(),
+                "test.c".to_string(),
+                "test.d".to_string()
+            ]
+        );
+        assert_eq!(
+            processor.get_target_table_names(),
+            vec!["test.my_table".to_string(), "test.b".to_string()]
+        );
+        assert_eq!(processor.get_directory_targets(), vec!["/tmp/out"]);
     }
 
     #[test]
-    fn test_use_with_join_query() {
-        let query = "use test_db; select t1.id, t2.name from test.table1 t1 join test.table2 t2 on t1.id = t2.id";
+    fn test_lowercase_keeps_string_literals() {
+        let query = r#"SELECT * FROM Test.T WHERE name = 'John' AND tag = "MiXed"; INSERT OVERWRITE TABLE test.p PARTITION (dt='2023-AB') SELECT * FROM test.S"#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        assert_eq!(
+            processor.get_table_names(),
+            vec!["test.t".to_string(), "test.s".to_string()]
+        );
+        let statements = processor.get_statements();
+        assert_eq!(
+            statements[0].sql,
+            r#"select * from test.t where name = 'John' and tag = "MiXed""#
+        );
+        assert!(statements[1].sql.contains("partition (dt='2023-AB')"));
+    }
+
+    #[test]
+    fn test_lowercase_ignores_quotes_in_comments() {
+        assert_eq!(
+            HiveSqlParser::lowercase_outside_literals(
+                "-- DON'T\nSELECT 'A' FROM T /* IT'S */ WHERE `X` = 1",
+                &['`']
+            ),
+            "-- don't\nselect 'A' from t /* it's */ where `x` = 1"
+        );
+    }
+
+    #[test]
+    fn test_comma_separated_derived_tables() {
+        let query =
+            r#"select * from (select * from test.a) x, (select * from test.b) y where x.id = y.id"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(2, table_names.len());
-        assert!(table_names.contains(&"test.table1".to_string()));
-        assert!(table_names.contains(&"test.table2".to_string()));
+        assert_eq!(
+            table_names,
+            vec!["test.a".to_string(), "test.b".to_string()]
+        );
     }
 
     #[test]
-    fn test_use_with_complex_join_query() {
-        let query = "use test_db; select t1.id, t2.name from test.table1 t1 join test.table2 t2 on t1.id = t2.id left join (select * from test.table3 a, test.table9 b where a.id=b.id ) t3 on t2.name =t3.name";
+    fn test_possible_cte_leaks() {
+        let query =
+            r#"use test; with c as (select * from test.a) select * from c; select * from c"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(4, table_names.len());
-        assert!(table_names.contains(&"test.table1".to_string()));
-        assert!(table_names.contains(&"test.table2".to_string()));
-        assert!(table_names.contains(&"test.table3".to_string()));
-        assert!(table_names.contains(&"test.table9".to_string()));
+        assert_eq!(
+            table_names,
+            vec!["test.a".to_string(), "test.c".to_string()]
+        );
+        assert_eq!(processor.get_possible_cte_leaks(), vec!["c".to_string()]);
+    }
+
+    #[test]
+    fn test_comment_markers_inside_literals() {
+        let query = r#"select '--not a comment', '/* nor this */' from test.t -- trailing
+        where `a--b` = 1 /* block */ and c = "x -- y""#;
+        let mut processor = HiveSqlParser::new();
+        processor.parse(query).unwrap();
+        assert_eq!(processor.get_table_names(), vec!["test.t".to_string()]);
+        assert_eq!(
+            processor.get_statements()[0].sql,
+            "select '--not a comment', '/* nor this */' from test.t\nwhere `a--b` = 1  and c = \"x -- y\""
+        );
     }
 
     #[test]
-    fn test_use_with_exists_query() {
-        let query = "use test_db; select id, name from test.my_table where exists (select 1 from test.another_table where another_table.id = my_table.id)";
+    fn test_comment_marker_in_backtick_table() {
+        let query = r#"select * from test.`t--1` join test.`t/*2*/` on 1 = 1"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
-        let table_names = processor.get_table_names();
-        assert_eq!(2, table_names.len());
-        assert!(table_names.contains(&"test.my_table".to_string()));
-        assert!(table_names.contains(&"test.another_table".to_string()));
+        assert_eq!(
+            processor.get_table_names(),
+            vec!["test.t--1".to_string(), "test.t/*2*/".to_string()]
+        );
     }
 
     #[test]
-    fn test_use_with_in_query() {
-        let query = "use test_db; select id, name from test.my_table where id in (select id from test.filter_table)";
+    fn test_distinct_on_subquery() {
+        let query = r#"select distinct on ((select k from test.cfg)) a from test.t"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(2, table_names.len());
-        assert!(table_names.contains(&"test.my_table".to_string()));
-        assert!(table_names.contains(&"test.filter_table".to_string()));
+        assert_eq!(
+            table_names,
+            vec!["test.cfg".to_string(), "test.t".to_string()]
+        );
     }
 
     #[test]
-    fn test_use_with_group_by_query() {
-        let query = "use test_db; select id, count(*) from test.my_table group by id";
+    fn test_delete_plain() {
+        let query = r#"DELETE FROM test.my_table WHERE id < 10"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
-        let table_names = processor.get_table_names();
-        assert_eq!(1, table_names.len());
-        assert!(table_names.contains(&"test.my_table".to_string()));
+        assert!(processor.get_table_names().is_empty());
+        assert_eq!(
+            processor.get_target_table_names(),
+            vec!["test.my_table".to_string()]
+        );
     }
 
     #[test]
-    fn test_use_with_order_by_query() {
-        let query = "use test_db; select id, name from test.my_table order by id desc";
+    fn test_delete_with_in_subquery() {
+        let query = r#"delete from test.my_table where id in (select id from test.other)"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
-        let table_names = processor.get_table_names();
-        assert_eq!(1, table_names.len());
-        assert!(table_names.contains(&"test.my_table".to_string()));
+        assert_eq!(processor.get_table_names(), vec!["test.other".to_string()]);
+        assert_eq!(
+            processor.get_target_table_names(),
+            vec!["test.my_table".to_string()]
+        );
     }
 
     #[test]
-    fn test_use_with_having_query() {
-        let query =
-            "use test_db; select id, count(*) as cnt from test.my_table group by id having cnt > 5";
+    fn test_error_statement_index() {
+        let query = r#"select * from test.a; select from where; select * from test.b"#;
         let mut processor = HiveSqlParser::new();
-        processor.parse(query).unwrap();
-        let table_names = processor.get_table_names();
-        assert_eq!(1, table_names.len());
-        assert!(table_names.contains(&"test.my_table".to_string()));
+        assert!(processor.parse(query).is_err());
+        let errors = processor.get_errors();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, 1);
+        assert!(matches!(
+            &errors[0].1,
+            ParseError::Syntax { statement, .. } if statement == "select from where"
+        ));
     }
 
     #[test]
-    fn test_use_with_partitioned_table() {
-        let query = "use test_db; CREATE TABLE test.partitioned_table (id INT, name STRING) PARTITIONED BY (dt STRING)";
+    fn test_update_with_subqueries() {
+        let query = r#"UPDATE test.my_table SET x = 1 WHERE id IN (SELECT id FROM test.src)"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
-        let table_names = processor.get_table_names();
-        assert_eq!(0, table_names.len());
+        assert_eq!(processor.get_table_names(), vec!["test.src".to_string()]);
+        assert_eq!(
+            processor.get_target_table_names(),
+            vec!["test.my_table".to_string()]
+        );
     }
 
     #[test]
-    fn test_use_with_insert_into_partition() {
-        let query = "use test_db; INSERT INTO TABLE test.partitioned_table PARTITION (dt='2023-05-01') SELECT id, name FROM test.source_table";
+    fn test_update_with_correlated_subquery() {
+        let query = r#"update test.my_table set x = (select max(v) from test.m)
+        where exists (select 1 from test.src s where s.k = my_table.k)"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
-        let table_names = processor.get_table_names();
-        assert_eq!(1, table_names.len());
-        assert!(table_names.contains(&"test.source_table".to_string()));
+        assert_eq!(
+            processor.get_table_names(),
+            vec!["test.m".to_string(), "test.src".to_string()]
+        );
+        assert_eq!(
+            processor.get_target_table_names(),
+            vec!["test.my_table".to_string()]
+        );
     }
 
     #[test]
-    fn test_use_with_create_external_table() {
-        let query = "use test_db; CREATE EXTERNAL TABLE test.external_table (id INT, name STRING) STORED AS PARQUET LOCATION '/path/to/data'";
+    fn test_group_by_subquery() {
+        // sqlparser 的 Hive 方言不支持 GROUPING SETS，这里用普通的 group by 子查询覆盖同一路径
+        let query = r#"select a from test.t group by a, (select k from test.cfg)"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(0, table_names.len());
+        assert_eq!(
+            table_names,
+            vec!["test.t".to_string(), "test.cfg".to_string()]
+        );
     }
 
     #[test]
-    fn test_use_with_bucketed_table() {
-        let query = r#"
-CREATE TABLE user_info_bucketed(user_id BIGINT, firstname STRING, lastname STRING)
-COMMENT 'A bucketed copy of user_info'
-PARTITIONED BY(ds STRING)
-CLUSTERED BY(user_id) INTO 256 BUCKETS;"#;
+    fn test_analyze() {
+        let query = r#"insert into test.t select * from test.a; insert overwrite table dw.u select * from test.t"#;
+        let result = HiveSqlParser::analyze(query).unwrap();
+        assert_eq!(
+            result.sources,
+            vec!["test.a".to_string(), "test.t".to_string()]
+        );
+        assert_eq!(
+            result.targets,
+            vec!["test.t".to_string(), "dw.u".to_string()]
+        );
+        assert_eq!(result.databases, vec!["test".to_string(), "dw".to_string()]);
+        assert_eq!(result.statements.len(), 2);
+        assert_eq!(
+            result.statements[1].source_tables,
+            vec!["test.t".to_string()]
+        );
+        assert_eq!(result.statements[1].target_tables, vec!["dw.u".to_string()]);
+        assert!(result.errors.is_empty());
+        assert!(matches!(
+            HiveSqlParser::analyze("select from where"),
+            Err(ParseError::Syntax { .. })
+        ));
+    }
+
+    #[test]
+    fn test_analyze_collects_errors() {
+        let query = r#"select * from test.a; select from where; select * from test.b"#;
+        let result = HiveSqlParser::analyze(query).unwrap();
+        assert_eq!(
+            result.sources,
+            vec!["test.a".to_string(), "test.b".to_string()]
+        );
+        assert_eq!(result.statements.len(), 2);
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].0, 1);
+        assert!(matches!(result.errors[0].1, ParseError::Syntax { .. }));
+    }
+
+    #[test]
+    fn test_multi_insert() {
+        let query = r#"FROM test.src
+        INSERT OVERWRITE TABLE test.a SELECT x
+        INSERT INTO TABLE test.b SELECT y WHERE y IN (SELECT id FROM test.ids)"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(0, table_names.len());
+        assert_eq!(
+            table_names,
+            vec!["test.src".to_string(), "test.ids".to_string()]
+        );
+        assert_eq!(
+            processor.get_target_table_names(),
+            vec!["test.a".to_string(), "test.b".to_string()]
+        );
     }
 
     #[test]
-    fn test_use_with_window_function() {
-        let query = "use test_db; SELECT id, name, AVG(salary) OVER (PARTITION BY department ORDER BY salary) AS avg_salary FROM test.employee_table";
+    fn test_unique_table_names() {
+        let query =
+            r#"select * from test.t a join test.t b on a.id = b.pid join test.s c on a.id = c.id"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
-        let table_names = processor.get_table_names();
-        assert_eq!(1, table_names.len());
-        assert!(table_names.contains(&"test.employee_table".to_string()));
+        assert_eq!(processor.get_table_names().len(), 3);
+        assert_eq!(
+            processor.get_unique_table_names(),
+            vec!["test.t".to_string(), "test.s".to_string()]
+        );
     }
 
     #[test]
-    fn test_use_with_lateral_view_explode() {
-        let query = "use test_db; SELECT t.id, t.name, item FROM test.table_with_array t LATERAL VIEW EXPLODE(t.items) itemTable AS item";
+    fn test_derived_table_with_insert_body() {
+        let query =
+            r#"select * from (insert into test.x select * from test.a) t; select * from test.c"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
-        let table_names = processor.get_table_names();
-        assert_eq!(1, table_names.len());
-        assert!(table_names.contains(&"test.table_with_array".to_string()));
+        assert_eq!(
+            processor.get_table_names(),
+            vec!["test.a".to_string(), "test.c".to_string()]
+        );
+        assert_eq!(
+            processor.get_target_table_names(),
+            vec!["test.x".to_string()]
+        );
+        let statements = processor.get_statements();
+        assert_eq!(statements[0].source_tables, vec!["test.a".to_string()]);
+        assert_eq!(statements[0].target_tables, vec!["test.x".to_string()]);
+        assert_eq!(statements[1].source_tables, vec!["test.c".to_string()]);
+        assert!(statements[1].target_tables.is_empty());
     }
 
     #[test]
-    fn test_use_with_complex_union_query() {
-        let query = "SET hive.exec.parallel=true;\n\
-                     use test_db; SELECT id, name FROM test.table1 UNION ALL SELECT id, name FROM test.table2 UNION SELECT id, name FROM test.table3";
+    fn test_reset_between_batches() {
+        let mut processor = HiveSqlParser::new();
+        processor
+            .parse("use db1; insert into t select * from s")
+            .unwrap();
+        assert_eq!(processor.get_table_names(), vec!["db1.s".to_string()]);
+
+        processor.reset();
+        assert!(processor.get_table_names().is_empty());
+        assert!(processor.get_target_table_names().is_empty());
+        assert!(processor.get_statements().is_empty());
+
+        processor.parse("select * from u").unwrap();
+        assert_eq!(processor.get_table_names(), vec!["default.u".to_string()]);
+    }
+
+    #[test]
+    fn test_table_function_subquery_argument() {
+        let query =
+            r#"select * from table_func((select s from test.cfg)) f join test.t on f.id = t.id"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(3, table_names.len());
-        assert!(table_names.contains(&"test.table1".to_string()));
-        assert!(table_names.contains(&"test.table2".to_string()));
-        assert!(table_names.contains(&"test.table3".to_string()));
+        assert_eq!(
+            table_names,
+            vec!["test.cfg".to_string(), "test.t".to_string()]
+        );
     }
 
     #[test]
-    fn test_use_with_union_in_cte() {
-        let query = "use test_db; WITH cte AS (SELECT id, name FROM test.table1 UNION ALL SELECT id, name FROM test.table2) SELECT * FROM cte";
+    fn test_external_table_location() {
+        let query = r#"create external table test.t (id int, name string) location '/data/t';
+        create table test.m (id int) location '/data/m'"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
-        let table_names = processor.get_table_names();
-        assert_eq!(2, table_names.len());
-        assert!(table_names.contains(&"test.table1".to_string()));
-        assert!(table_names.contains(&"test.table2".to_string()));
+        assert_eq!(
+            processor.get_external_locations(),
+            vec![("test.t".to_string(), "/data/t".to_string())]
+        );
     }
 
     #[test]
-    fn test_multi_line_comment() {
-        let query = r#"/* This is a multi-line comment
-                       spanning multiple lines */
-                       SELECT * FROM test.table1;"#;
+    fn test_semicolon_inside_string_literal() {
+        let query = r#"SELECT ';' AS sep, "a;b" FROM test.t; select `x;y` from test.s"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
-        let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 1);
-        assert!(table_names.contains(&"test.table1".to_string()));
+        assert_eq!(
+            processor.get_table_names(),
+            vec!["test.t".to_string(), "test.s".to_string()]
+        );
+        assert_eq!(processor.get_statements().len(), 2);
     }
 
     #[test]
-    fn test_comment_within_join_clause() {
-        let query = r#"SELECT t1.*, t2.* FROM test.table1 t1
-                       JOIN /* This is a comment within a JOIN clause */ test.table2 t2
-                       ON t1.id = t2.id;"#;
+    fn test_semicolon_inside_block_comment() {
+        let query =
+            r#"select * from test.t /* first; second; */ where id > 1; select * from test.s"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
-        let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 2);
-        assert!(table_names.contains(&"test.table1".to_string()));
-        assert!(table_names.contains(&"test.table2".to_string()));
+        assert_eq!(
+            processor.get_table_names(),
+            vec!["test.t".to_string(), "test.s".to_string()]
+        );
+        assert_eq!(processor.get_statements().len(), 2);
     }
 
     #[test]
-    fn test_comment_within_subquery() {
-        let query = r#"SELECT * FROM (
-                           SELECT id, name -- This is a comment within a subquery
-                           FROM test.table1
-                           WHERE id > 10
-                       ) subquery;"#;
+    fn test_in_values_with_subquery() {
+        let query = r#"select * from test.s where id in (values (1),(2),(select x from test.t))"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 1);
-        assert!(table_names.contains(&"test.table1".to_string()));
+        assert_eq!(
+            table_names,
+            vec!["test.s".to_string(), "test.t".to_string()]
+        );
     }
 
     #[test]
-    fn test_comment_within_cte() {
-        let query = r#"WITH cte AS (
-                           -- This is a comment within a CTE
-                           SELECT id, name FROM test.table1
-                       )
-                       SELECT * FROM cte;"#;
+    fn test_in_list_with_subquery() {
+        let query = r#"select * from test.s where id in (1, (select x from test.t))"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 1);
-        assert!(table_names.contains(&"test.table1".to_string()));
+        assert_eq!(
+            table_names,
+            vec!["test.s".to_string(), "test.t".to_string()]
+        );
     }
 
     #[test]
-    fn test_comment_within_insert_statement() {
-        let query = r#"INSERT INTO test.table2
-                       -- This is a comment within an INSERT statement
-                       SELECT * FROM test.table1;"#;
+    fn test_parse_lenient() {
+        let query = r#"select * from test.a; this is not sql; select * from test.b"#;
+        let mut processor = HiveSqlParser::new();
+        let (tables, errors) = processor.parse_lenient(query);
+        assert_eq!(tables, vec!["test.a".to_string(), "test.b".to_string()]);
+        assert_eq!(errors.len(), 1);
+        match &errors[0] {
+            ParseError::Syntax { statement, message } => {
+                assert_eq!(statement, "this is not sql");
+                assert!(!message.is_empty());
+            }
+            e => panic!("unexpected error: {:?}", e),
+        }
+        assert_eq!(processor.get_errors()[0].0, 1);
+    }
+
+    #[test]
+    fn test_parse_lenient_discards_failed_statement() {
+        let query = r#"select * from a; use test; select * from b"#;
+        let mut processor = HiveSqlParser::new().with_strict_mode(true);
+        let (tables, errors) = processor.parse_lenient(query);
+        assert_eq!(tables, vec!["test.b".to_string()]);
+        assert_eq!(
+            errors,
+            vec![ParseError::UnqualifiedWithoutDatabase {
+                table: "a".to_string()
+            }]
+        );
+        assert_eq!(processor.get_statements().len(), 1);
+    }
+
+    #[test]
+    fn test_get_cte_names() {
+        let query = "with temp_a as (select * from test.table5), temp_b as (select * from test.table6), temp_c as (select * from temp_a join temp_b on temp_a.id=temp_b.id)select t1.id, t2.name from test.table1 t1 join test.table2 t2 on t1.id = t2.id left join (select * from test.table3 a, test.table9 b where a.id=b.id ) t3 on t2.name =t3.name right join temp_c t4 on t1.id=t4.id";
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
-        let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 1);
-        assert!(table_names.contains(&"test.table1".to_string()));
+        assert_eq!(
+            processor.get_cte_names(),
+            vec![
+                "temp_a".to_string(),
+                "temp_b".to_string(),
+                "temp_c".to_string()
+            ]
+        );
     }
 
     #[test]
-    fn test_multiple_comments_in_complex_query() {
-        let query = r#"-- Initial comment
-                       WITH cte AS (
-                           SELECT id, name -- Comment in CTE
-                           FROM test.table1
-                       )
-                       SELECT c.*, t2.* -- Comment in main query
-                       FROM cte c
-                       JOIN /* Multi-line comment
-                               in JOIN clause */ test.table2 t2
-                       ON c.id = t2.id
-                       WHERE c.id > 10; -- Final comment"#;
+    fn test_extract_raw_table_tokens() {
+        let query = r#"SELECT t1.id FROM test.table1 t1 JOIN (select * from test.table2) t2 ON t1.id = t2.id"#;
+        let raw = HiveSqlParser::extract_raw_table_tokens(query);
+        assert_eq!(
+            raw,
+            vec!["test.table1".to_string(), "test.table2".to_string()]
+        );
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
-        let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 2);
-        assert!(table_names.contains(&"test.table1".to_string()));
-        assert!(table_names.contains(&"test.table2".to_string()));
+        assert_eq!(raw, processor.get_table_names());
     }
 
     #[test]
-    fn test_window_function_with_partition_and_order_by() {
-        let query = r#"-- Calculate running total of sales for each store
-                       USE sales_db;
-                       SELECT store_id, sale_date, sales_amount,
-                              SUM(sales_amount) OVER (PARTITION BY store_id ORDER BY sale_date) AS running_total
-                       FROM sales_table;"#;
+    fn test_except_query() {
+        let query = r#"SELECT id FROM test.a EXCEPT SELECT id FROM test.b"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 1);
-        assert!(table_names.contains(&"sales_db.sales_table".to_string()));
+        assert_eq!(
+            table_names,
+            vec!["test.a".to_string(), "test.b".to_string()]
+        );
     }
 
     #[test]
-    fn test_window_function_with_rows_between() {
-        let query = r#"USE inventory_db;
-                       -- Calculate 7-day moving average of inventory levels
-                       SELECT product_id, `date`, inventory_level,
-                              AVG(inventory_level) OVER (
-                                  PARTITION BY product_id
-                                  ORDER BY `date`
-                                  ROWS BETWEEN 6 PRECEDING AND CURRENT ROW
-                              ) AS moving_average
-                       FROM inventory_table;"#;
+    fn test_intersect_query() {
+        let query = r#"select id from test.a intersect select id from test.b intersect select id from test.c"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 1);
-        assert!(table_names.contains(&"inventory_db.inventory_table".to_string()));
+        assert_eq!(
+            table_names,
+            vec![
+                "test.a".to_string(),
+                "test.b".to_string(),
+                "test.c".to_string()
+            ]
+        );
     }
 
     #[test]
-    fn test_multiple_window_functions_with_different_partitions() {
-        let query = r#"-- Calculate rank and dense rank within each department and overall
-                       USE hr_db;
-                       SELECT employee_id, department, salary,
-                              RANK() OVER (PARTITION BY department ORDER BY salary DESC) AS dept_rank,
-                              DENSE_RANK() OVER (ORDER BY salary DESC) AS overall_dense_rank
-                       FROM employee_table;"#;
+    fn test_ctas_target_named_like_cte() {
+        // sqlparser 不支持 `with t as (...) create table t ...`，这里用等价的 CTAS 内部 WITH 写法
+        let query = r#"create table t as with t as (select * from test.a) select * from t"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
-        let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 1);
-        assert!(table_names.contains(&"hr_db.employee_table".to_string()));
+        assert_eq!(processor.get_table_names(), vec!["test.a".to_string()]);
+        assert_eq!(
+            processor.get_target_table_names(),
+            vec!["default.t".to_string()]
+        );
     }
 
     #[test]
-    fn test_window_function_in_subquery() {
-        let query = r#"USE sales_db;
-                       -- Find top 5 products by sales in each category
-                       SELECT * FROM (
-                           SELECT product_id, category, total_sales,
-                                  DENSE_RANK() OVER (PARTITION BY category ORDER BY total_sales DESC) AS sales_rank
-                           FROM (
-                               -- Calculate total sales for each product
-                               SELECT product_id, category, SUM(sales_amount) AS total_sales
-                               FROM sales_table
-                               GROUP BY product_id, category
-                           ) t
-                       ) t2
-                       WHERE sales_rank <= 5;"#;
+    fn test_derived_table_then_join() {
+        let query = r#"select * from (select * from test.a) x join test.b on x.id = b.id"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 1);
-        assert!(table_names.contains(&"sales_db.sales_table".to_string()));
+        assert_eq!(
+            table_names,
+            vec!["test.a".to_string(), "test.b".to_string()]
+        );
     }
 
     #[test]
-    fn test_window_function_with_join() {
-        let query = r#"-- Calculate employee's salary percentile within their department
-                       USE hr_db;
-                       SELECT e.employee_id, e.department, e.salary, d.dept_name,
-                              CUME_DIST() OVER (PARTITION BY e.department ORDER BY e.salary) AS salary_percentile
-                       FROM employee_table e
-                       JOIN test.department_table d ON e.department = d.dept_id;"#;
+    fn test_double_quote_identifiers() {
+        let query = r#"select * from "Test"."T""#;
+        let mut processor = HiveSqlParser::new().with_identifier_quotes(&['`', '"']);
+        processor.parse(query).unwrap();
+        assert_eq!(processor.get_table_names(), vec!["test.t".to_string()]);
+
+        // 默认只把反引号当作标识符引号，双引号内容保留原样
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
-        let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 2);
-        assert!(table_names.contains(&"hr_db.employee_table".to_string()));
-        assert!(table_names.contains(&"test.department_table".to_string()));
+        assert_eq!(processor.get_table_names(), vec!["Test.T".to_string()]);
     }
 
     #[test]
-    fn test_multiple_uses_with_union() {
-        let query = r#"-- Select from table in db1
-                       USE db1;
-                       SELECT * FROM table1
-                       UNION ALL
-                       -- Select from table in db2
-                       SELECT * FROM db2.table2;"#;
+    fn test_where_two_subqueries_joined_by_and() {
+        let query = r#"select * from test.a a where a.id in (select id from test.b) and a.x = (select max(x) from test.c)"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 2);
-        assert!(table_names.contains(&"db1.table1".to_string()));
-        assert!(table_names.contains(&"db2.table2".to_string()));
+        assert_eq!(
+            table_names,
+            vec![
+                "test.a".to_string(),
+                "test.b".to_string(),
+                "test.c".to_string()
+            ]
+        );
     }
 
     #[test]
-    fn test_multiple_uses_with_join() {
-        let query = r#"-- Join tables from different databases
-                       USE db1;
-                       SELECT t1.*, t2.*
-                       FROM table1 t1
-                       JOIN db2.table2 t2 ON t1.id = t2.id;"#;
+    fn test_where_nested_expression_walker() {
+        let query = r#"select * from test.a where (not exists (select 1 from test.b) or a.id between (select min(id) from test.c) and 10)
+        and coalesce((select v from test.d), 0) > 0 and cast((select w from test.e) as int) is not null"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 2);
-        assert!(table_names.contains(&"db1.table1".to_string()));
-        assert!(table_names.contains(&"db2.table2".to_string()));
+        assert_eq!(
+            table_names,
+            vec![
+                "test.a".to_string(),
+                "test.b".to_string(),
+                "test.c".to_string(),
+                "test.d".to_string(),
+                "test.e".to_string()
+            ]
+        );
     }
 
     #[test]
-    fn test_multiple_uses_in_cte() {
-        let query = r#"-- CTE with tables from different databases
-                      USE db1;
-                       WITH cte1 AS (
-                           SELECT * FROM table1
-                       ),
-                       cte2 AS (
-                           SELECT * FROM db2.table2
-                       )
-                       SELECT * FROM cte1
-                       UNION ALL
-                       SELECT * FROM cte2;"#;
+    fn test_merge_not_matched_insert_values_subquery() {
+        let query = r#"merge into test.t using test.s on t.id = s.id
+        when matched then update set v = s.v
+        when not matched then insert values ((select v from test.cfg))"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
-        let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 2);
-        assert!(table_names.contains(&"db1.table1".to_string()));
-        assert!(table_names.contains(&"db2.table2".to_string()));
+        assert_eq!(
+            processor.get_table_usages(),
+            vec![
+                ("test.s".to_string(), TableUsage::Read),
+                ("test.cfg".to_string(), TableUsage::Read),
+                ("test.t".to_string(), TableUsage::Write)
+            ]
+        );
     }
 
     #[test]
-    fn test_comment_before_and_after_sql() {
-        let query = r#"-- This is a comment before the SQL
-                       SELECT * FROM test.table1;
-                       -- This is a comment after the SQL"#;
+    fn test_where_not_exists_subquery() {
+        let query =
+            r#"select * from test.a where not exists (select 1 from test.t where t.id = a.id)"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 1);
-        assert!(table_names.contains(&"test.table1".to_string()));
+        assert_eq!(table_names, vec!["test.a", "test.t"]);
     }
 
     #[test]
-    fn test_comment_between_sql_statements() {
-        let query = r#"SELECT * FROM test.table1;
-                       -- This is a comment between SQL statements
-                       SELECT * FROM test.table2;"#;
+    fn test_where_not_in_subquery() {
+        let query = r#"select * from test.a where a.id not in (select id from test.t)"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 2);
-        assert!(table_names.contains(&"test.table1".to_string()));
-        assert!(table_names.contains(&"test.table2".to_string()));
+        assert_eq!(table_names, vec!["test.a", "test.t"]);
     }
 
     #[test]
-    fn test_comment_within_sql() {
-        let query = r#"SELECT * FROM test.table1 -- This is an inline comment
-                       WHERE id > 10;"#;
+    fn test_join_on_subquery() {
+        let query = r#"select * from test.a join test.b on a.id = (select max(id) from test.c)"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 1);
-        assert!(table_names.contains(&"test.table1".to_string()));
+        assert_eq!(table_names, vec!["test.a", "test.b", "test.c"]);
     }
 
     #[test]
-    fn test_multiple_statements_with_comment() {
-        let query = r#"set tez.queue.name=root.test; -- set queue name 
-                       -- First, let's select from test.my_table
-                       select * from test.my_table;
-                       /* Now, let's switch to another database
-                       select * from 
-                       xhw.test where exists (select id from txs.good)  
-                       hello world */
-                       use test_db;
-                       -- Finally, let's select from test.another_table1
-                       SET hive.exec.dynamic.partition=true;
-                       SET hive.exec.dynamic.partition.mode=nonstrict;
-                       select * from another_table
-                       -- Finally, let's select from test.another_table1;
-                       SET mapreduce.job.name='My Sales Report';"#;
+    fn test_merge_table_roles() {
+        let query = r#"merge into test.t using test.s on t.id = s.id
+        when matched and s.v in (select v from test.cfg) then delete
+        when not matched then insert values (s.id, s.v)"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
-        let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 2);
-        assert!(table_names.contains(&"test.my_table".to_string()));
-        assert!(table_names.contains(&"test_db.another_table".to_string()));
+        assert_eq!(
+            processor.get_merge_table_roles(),
+            vec![
+                ("test.t".to_string(), MergeRole::Target),
+                ("test.s".to_string(), MergeRole::Source),
+                ("test.cfg".to_string(), MergeRole::ClauseRef)
+            ]
+        );
     }
 
     #[test]
-    fn test_multiple_uses_with_case_statement() {
-        let query = r#"SET hive.fetch.task.conversion=more;
-                       USE sales_db;
-                       SELECT s.product_id,
-                              CASE
-                                  WHEN p.category = 'Electronics' THEN 'Tech'
-                                  WHEN p.category = 'Clothing' THEN 'Apparel'
-                                  ELSE 'Other'
-                              END AS category_group,
-                              SUM(s.amount) AS total_sales
-                       FROM sales_table s
-                       JOIN product_db.product_table p ON s.product_id = p.id
-                       GROUP BY s.product_id, category_group;
-                       SET hive.exec.dynamic.partition=true;
-                       SET hive.exec.dynamic.partition.mode=nonstrict;
-                       USE db1;
-                       SELECT *
-                       FROM table1 t1
-                       WHERE EXISTS (
-                         -- Subquery from another database
-                         SELECT 1 FROM db2.table2 t2 WHERE t2.id = t1.id
-                       );
-                       use hello;"#;
+    fn test_only_semicolons() {
+        for query in [";", ";;;"] {
+            let mut processor = HiveSqlParser::new();
+            assert!(processor.parse(query).is_ok());
+            assert!(processor.get_table_names().is_empty());
+            assert!(processor.get_target_table_names().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_nested_join() {
+        let query = r#"select * from (test.a join test.b on a.id = b.id)"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 4);
-        assert!(table_names.contains(&"sales_db.sales_table".to_string()));
-        assert!(table_names.contains(&"product_db.product_table".to_string()));
-        assert!(table_names.contains(&"db1.table1".to_string()));
-        assert!(table_names.contains(&"db2.table2".to_string()));
+        assert_eq!(table_names, vec!["test.a", "test.b"]);
     }
 
     #[test]
-    fn test_multiple_uses_with_cte() {
-        let query = r#"-- Define CTE in one database
-                       USE db1;
-                       WITH cte AS (
-                         SELECT * FROM table1
-                       )
-                       SELECT * FROM cte c
-                       JOIN db2.table2 t ON c.id = t.id;"#;
+    fn test_nested_join_with_outer_join() {
+        let query =
+            r#"select * from (test.a join test.b on a.id = b.id) left join test.c on a.id = c.id"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 2);
-        assert!(table_names.contains(&"db1.table1".to_string()));
-        assert!(table_names.contains(&"db2.table2".to_string()));
+        assert_eq!(table_names, vec!["test.a", "test.b", "test.c"]);
     }
 
     #[test]
-    fn test_multiple_uses_with_group_by_and_having() {
-        let query = r#"USE sales_db;
-                       SELECT product, SUM(amount) AS total_sales
-                       FROM sales_table
-                       GROUP BY product
-                       HAVING SUM(amount) > (
-                         SELECT AVG(quantity * price) FROM inventory_db.inventory_table
-                       );"#;
+    fn test_insert_overwrite_where_having_subqueries() {
+        let query = r#"insert overwrite table test.t
+        select a.k, count(*) from test.a join test.b on a.id = b.id
+        where a.id in (select id from test.c)
+        group by a.k
+        having count(*) > (select avg(cnt) from test.d)"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
-        let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 2);
-        assert!(table_names.contains(&"sales_db.sales_table".to_string()));
-        assert!(table_names.contains(&"inventory_db.inventory_table".to_string()));
+        assert_eq!(
+            processor.get_table_usages(),
+            vec![
+                ("test.a".to_string(), TableUsage::Read),
+                ("test.b".to_string(), TableUsage::Read),
+                ("test.c".to_string(), TableUsage::Read),
+                ("test.d".to_string(), TableUsage::Read),
+                ("test.t".to_string(), TableUsage::Write)
+            ]
+        );
     }
 
     #[test]
-    fn test_multiple_uses_with_lateral_view() {
-        let query = r#"-- Select from table in db1
-                       USE db1;
-                       SELECT t.id, t.col1, lv.col2
-                       FROM table1 t
-                        -- Join with table from db2
-                       JOIN db2.table2 t2 ON t.id = t2.id
-                       LATERAL VIEW explode(split(t.col2, ',')) lv AS col2;"#;
-        let mut processor = HiveSqlParser::new();
+    fn test_ignored_statements() {
+        let query = r#"create view test.v as select * from test.a;
+        select * from test.b"#;
+        let mut processor = HiveSqlParser::new().with_ignored_statements(&["CreateView"]);
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 2);
-        assert!(table_names.contains(&"db1.table1".to_string()));
-        assert!(table_names.contains(&"db2.table2".to_string()));
+        assert_eq!(table_names, vec!["test.b"]);
+        assert!(processor.get_target_table_names().is_empty());
     }
 
     #[test]
-    fn test_multiple_uses_with_subquery() {
-        let query = r#"USE db1;
-                       SELECT *
-                       FROM table1
-                       WHERE id IN (
-                         -- Subquery from another database
-                         SELECT id FROM db2.table2
-                       );"#;
+    fn test_nested_join_of_three_tables() {
+        let query =
+            r#"select * from ((test.a join test.b on a.id = b.id) join test.c on b.id = c.id)"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 2);
-        assert!(table_names.contains(&"db1.table1".to_string()));
-        assert!(table_names.contains(&"db2.table2".to_string()));
+        assert_eq!(table_names, vec!["test.a", "test.b", "test.c"]);
     }
 
     #[test]
-    fn test_multiple_uses_with_union_and_join() {
-        let query = r#"-- Union tables from different databases
-                       USE db1;
-                       SELECT * FROM table1
-                       UNION ALL
-                       SELECT * FROM db2.table2
-                       -- Join with table from another database
-                       JOIN db3.table3 ON db1.table1.id = db3.table3.id;"#;
+    fn test_three_part_name_join() {
+        let query = r#"select * from cat.db.a join cat.db.b on a.id = b.id"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 3);
-        assert!(table_names.contains(&"db1.table1".to_string()));
-        assert!(table_names.contains(&"db2.table2".to_string()));
-        assert!(table_names.contains(&"db3.table3".to_string()));
+        assert_eq!(table_names, vec!["cat.db.a", "cat.db.b"]);
     }
 
     #[test]
-    fn test_multiple_uses_with_window_function() {
-        let query = r#"-- Calculate running total sales
-                       USE sales_db;
-                       SELECT s.`date`, p.name, SUM(s.amount) OVER (ORDER BY s.`date`) AS running_total
-                       FROM sales_table s
-                       -- Join with product info from another database
-                       JOIN product_db.product_table p ON s.product_id = p.id;"#;
+    fn test_insert_values_target() {
+        let query = "insert into test.my_table values (1, 'John'), (2, 'Jane')";
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
-        let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 2);
-        assert!(table_names.contains(&"sales_db.sales_table".to_string()));
-        assert!(table_names.contains(&"product_db.product_table".to_string()));
+        assert!(processor.get_table_names().is_empty());
+        assert_eq!(processor.get_target_table_names(), vec!["test.my_table"]);
     }
 
     #[test]
-    fn test_parse_bucketed_table() {
-        let query = r#"CREATE TABLE test.bucketed_table (id INT, name STRING) CLUSTERED BY (id) INTO 4 BUCKETS"#;
+    fn test_select_from_values_constructor() {
+        let query = r#"select * from (values (1), (2)) t"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
-        let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 0);
+        assert!(processor.get_table_names().is_empty());
+        assert!(processor.get_target_table_names().is_empty());
     }
 
     #[test]
-    fn test_parse_complex_join_with_subqueries() {
-        let query = r#"SELECT a.id, b.name, c.value FROM 
-                       (SELECT id FROM test.table1 WHERE id > 100) a 
-                       JOIN test.table2 b ON a.id = b.id 
-                       LEFT JOIN (SELECT id, MAX(value) as value FROM test.table3 GROUP BY id) c ON a.id = c.id"#;
+    fn test_table_names_with_stmt_index() {
+        let query = r#"set hive.exec.parallel=true;
+        select * from test.a;
+        insert into test.t select * from test.b join test.c on b.id = c.id"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
-        let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 3);
-        assert!(table_names.contains(&"test.table1".to_string()));
-        assert!(table_names.contains(&"test.table2".to_string()));
-        assert!(table_names.contains(&"test.table3".to_string()));
+        assert_eq!(
+            processor.get_table_names_with_stmt_index(),
+            vec![
+                (0, "test.a".to_string()),
+                (1, "test.b".to_string()),
+                (1, "test.c".to_string())
+            ]
+        );
     }
 
     #[test]
-    fn test_parse_complex_union_query() {
-        let query = r#"SELECT id, name FROM test.table1 UNION ALL SELECT id, name FROM test.table2 UNION SELECT id, name FROM test.table3"#;
+    fn test_cte_used_in_both_union_branches() {
+        let query = r#"with c as (select * from test.a) select * from c union all select * from c"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 3);
-        assert!(table_names.contains(&"test.table1".to_string()));
-        assert!(table_names.contains(&"test.table2".to_string()));
-        assert!(table_names.contains(&"test.table3".to_string()));
+        assert_eq!(table_names, vec!["test.a"]);
+        assert!(!processor.references_table("c"));
+        assert!(!processor.references_table("default.c"));
     }
 
     #[test]
-    fn test_parse_create_external_table() {
-        let query = r#"CREATE EXTERNAL TABLE test.external_table (id INT, name STRING) STORED AS PARQUET LOCATION '/path/to/data'"#;
+    fn test_where_doubly_parenthesized_in_subquery() {
+        let query = r#"select * from test.a where ((a.id in (select id from test.t)))"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 0);
+        assert_eq!(table_names, vec!["test.a", "test.t"]);
     }
 
     #[test]
-    fn test_parse_create_view_query() {
-        let query =
-            r#"CREATE VIEW test.my_view AS SELECT id, name FROM test.base_table WHERE id > 100"#;
+    fn test_create_table_like() {
+        let query = r#"create table a like test.template"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 1);
-        assert!(table_names.contains(&"test.base_table".to_string()));
+        assert_eq!(table_names, vec!["test.template"]);
+        assert_eq!(processor.get_target_table_names(), vec!["default.a"]);
     }
 
     #[test]
-    fn test_parse_insert_into_partition() {
-        let query = r#"INSERT INTO TABLE test.partitioned_table PARTITION (dt='2023-05-01') SELECT id, name FROM test.source_table"#;
+    fn test_ctas_with_leading_with_clause() {
+        let query = r#"create table test.t as with c as (select * from test.src) select * from c"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 1);
-        assert!(table_names.contains(&"test.source_table".to_string()));
+        assert_eq!(table_names, vec!["test.src"]);
+        assert_eq!(processor.get_target_table_names(), vec!["test.t"]);
     }
 
     #[test]
-    fn test_parse_insert_overwrite_directory() {
-        let query =
-            r#"INSERT OVERWRITE DIRECTORY '/output/path' SELECT id, name FROM test.source_table"#;
+    fn test_read_then_dropped() {
+        let query = r#"with c as (select * from test.t) select * from c;
+        drop table test.t;
+        drop table test.other"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
-        let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 1);
-        assert!(table_names.contains(&"test.source_table".to_string()));
+        assert_eq!(processor.get_table_names(), vec!["test.t"]);
+        assert_eq!(processor.get_read_then_dropped(), vec!["test.t"]);
     }
 
     #[test]
-    fn test_parse_lateral_view_explode() {
-        let query = r#"SELECT t.id, t.name, item FROM test.table_with_array t LATERAL VIEW EXPLODE(t.items) itemTable AS item"#;
+    fn test_insert_overwrite_union_all_branches() {
+        let query = r#"insert overwrite table test.t
+        select id from test.a union all select id from test.b"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 1);
-        assert!(table_names.contains(&"test.table_with_array".to_string()));
+        assert_eq!(table_names, vec!["test.a", "test.b"]);
+        assert_eq!(processor.get_target_table_names(), vec!["test.t"]);
     }
 
     #[test]
-    fn test_parse_partitioned_table() {
-        let query = r#"CREATE TABLE test.partitioned_table (id INT, name STRING) PARTITIONED BY (dt STRING)"#;
+    fn test_union_derived_table_with_where_subquery() {
+        let query = r#"select * from (select * from test.a union all select * from test.b) x
+        where x.id in (select id from test.c)"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 0);
+        assert_eq!(table_names, vec!["test.a", "test.b", "test.c"]);
     }
 
     #[test]
-    fn test_parse_window_function() {
-        let query = r#"SELECT id, name, AVG(salary) OVER (PARTITION BY department ORDER BY salary) AS avg_salary FROM test.employee_table"#;
-        let mut processor = HiveSqlParser::new();
-        processor.parse(query).unwrap();
+    fn test_clear_results_keeps_default_database() {
+        let mut processor = HiveSqlParser::new()
+            .with_default_database("ods")
+            .with_strict_mode(true);
+        processor.parse("use other; select * from t1").unwrap();
+        assert_eq!(processor.get_table_names(), vec!["other.t1"]);
+        processor.clear_results();
+        assert!(processor.get_table_names().is_empty());
+        assert!(processor.get_errors().is_empty());
+        processor.parse("select * from t2").unwrap();
+        assert_eq!(processor.get_table_names(), vec!["ods.t2"]);
+    }
+
+    #[test]
+    fn test_with_default_database() {
+        let mut processor = HiveSqlParser::new().with_default_database("mart");
+        processor
+            .parse("select * from t join dim.d on t.id = d.id")
+            .unwrap();
         let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 1);
-        assert!(table_names.contains(&"test.employee_table".to_string()));
+        assert_eq!(table_names, vec!["mart.t", "dim.d"]);
     }
 
     #[test]
-    fn test_select_with_comment() {
-        let query = r#"select id, name from test.my_table -- Select id, name from xxx.test \nwhere id > 10 -- Only records with id greater than 10"#;
+    fn test_strict_error_discards_partial_results() {
+        let mut processor = HiveSqlParser::new().with_strict_mode(true);
+        assert!(processor
+            .parse("insert into test.t select * from s")
+            .is_err());
+        assert!(processor.get_target_table_names().is_empty());
+        processor.parse("use x; select * from test.y").unwrap();
+        assert_eq!(processor.get_table_names(), vec!["test.y"]);
+        assert_eq!(processor.get_statements()[0].source_tables, vec!["test.y"]);
+    }
+
+    #[test]
+    fn test_lenient_rolls_back_failed_statement_side_effects() {
+        let mut processor = HiveSqlParser::new().with_strict_mode(true);
+        let (table_names, errors) = processor.parse_lenient(
+            "create temporary table tmp as select * from s; use d; select * from test.a",
+        );
+        assert_eq!(errors.len(), 1);
+        assert_eq!(table_names, vec!["test.a"]);
+        assert!(processor.get_temporary_tables().is_empty());
+        assert!(processor.get_target_table_names().is_empty());
+        assert!(!processor.contains_ddl());
+    }
+
+    #[test]
+    fn test_directory_target_kept_out_of_tables() {
+        let query = r#"insert overwrite directory '/tmp/out.d' select * from test.s"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
-        let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 1);
-        assert!(table_names.contains(&"test.my_table".to_string()));
+        assert!(processor.get_target_table_names().is_empty());
+        assert_eq!(processor.get_directory_targets(), vec!["/tmp/out.d"]);
+        let grouped = processor.get_grouped();
+        assert_eq!(grouped.len(), 1);
+        assert_eq!(grouped["test"], (vec!["s".to_string()], vec![]));
+        let (ordered, _) = processor.get_tables_topo_order();
+        assert_eq!(ordered, vec!["test.s"]);
     }
 
     #[test]
-    fn test_use_after_select_query() {
-        let query = r#"select id, name from test.my_table where id > 10; use test_db"#;
+    fn test_tablesample_inside_string_literal_kept() {
+        let query =
+            r#"select 'tablesample(x)' as c from test.t tablesample(bucket 1 out of 4 on id) s"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
-        let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 1);
-        assert_eq!(table_names[0], "test.my_table".to_string());
+        assert_eq!(processor.get_table_names(), vec!["test.t"]);
+        assert!(processor.get_statements()[0]
+            .sql
+            .contains("'tablesample(x)'"));
     }
 
     #[test]
-    fn test_use_before_select_query() {
-        let query = r#"use test_db; select id, name from test.my_table where id > 10"#;
-        let mut processor = HiveSqlParser::new();
+    fn test_strict_mode_accepts_routine_statements() {
+        let query = r#"use db; truncate table t; drop view v; analyze table t compute statistics"#;
+        let mut processor = HiveSqlParser::new().with_strict_mode(true);
         processor.parse(query).unwrap();
-        let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 1);
-        assert_eq!(table_names[0], "test.my_table".to_string());
+        assert!(processor.get_errors().is_empty());
     }
 
     #[test]
-    fn test_use_with_complex_join_with_subqueries() {
-        let query = r#"SET hive.exec.compress.output=true;
-                       SET mapreduce.output.fileoutputformat.compress.codec=org.apache.hadoop.io.compress.GzipCodec;
-                       SELECT id, name FROM test.table_1 UNION ALL SELECT id, name FROM table_2 UNION SELECT id, name FROM test.table_3;
-                       use test_db; SELECT a.id, b.name, c.value FROM 
-                       (SELECT id FROM test.table1 WHERE id > 100) a 
-                       JOIN table2 b ON a.id = b.id 
-                       LEFT JOIN (SELECT id, MAX(value) as value FROM test.table3 GROUP BY id) c ON a.id = c.id"#;
+    fn test_stmt_index_matches_get_statements() {
+        let query = r#"set a=1; use db; select * from a; select * from b"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
-        let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 6);
-        assert!(table_names.contains(&"test.table1".to_string()));
-        assert!(table_names.contains(&"test.table_1".to_string()));
-        assert!(table_names.contains(&"default.table_2".to_string()));
-        assert!(table_names.contains(&"test_db.table2".to_string()));
-        assert!(table_names.contains(&"test.table3".to_string()));
-        assert!(table_names.contains(&"test.table_3".to_string()));
+        assert_eq!(
+            processor.get_table_names_with_stmt_index(),
+            vec![(0, "db.a".to_string()), (1, "db.b".to_string())]
+        );
+        assert_eq!(
+            processor.sources_for_statement(0),
+            Some(vec!["db.a".to_string()])
+        );
     }
 
     #[test]
-    fn test_use_with_create_table() {
-        let query = r#"-- ok to test: select 1 from tst.xxx
-                       SET hive.enforce.bucketing=true; --- enforce buctket to true 
-                       CREATE TABLE users_bucketed (
-                         user_id INT, -- to comment 
-                         name STRING comment 'ddddd',
-                         age INT
-                       )
-                       CLUSTERED BY (user_id) INTO 4 BUCKETS;"#;
+    fn test_bucket_tokenizer_keeps_doubled_quote_literal() {
+        let query = r#"select * from test.t where name = 'it''s';
+        create table test.b (id int) clustered by (id) into 4 buckets stored as orc"#;
+        let mut processor = HiveSqlParser::new().with_bucket_regex(false);
+        processor.parse(query).unwrap();
+        assert_eq!(processor.get_table_names(), vec!["test.t"]);
+        assert_eq!(processor.get_target_table_names(), vec!["test.b"]);
+    }
+
+    #[test]
+    fn test_tablesample_with_doubled_quote_literal() {
+        let query =
+            r#"select * from test.t tablesample(bucket 1 out of 4 on id) where name = 'it''s'"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
-        let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 0);
+        assert_eq!(processor.get_table_names(), vec!["test.t"]);
     }
 
     #[test]
-    fn test_use_with_create_view_query() {
-        let query = r#"use test_db; CREATE VIEW test.my_view AS SELECT id, name FROM test.base_table WHERE id > 100"#;
+    fn test_multi_insert_with_doubled_quote_literal() {
+        let query = r#"from test.src
+        insert overwrite table test.a select * where c = 'it''s'
+        insert into table test.b select * where c = 'x'"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
-        let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 1);
-        assert!(table_names.contains(&"test.base_table".to_string()));
+        assert_eq!(processor.get_table_names(), vec!["test.src"]);
+        assert_eq!(processor.get_target_table_names(), vec!["test.a", "test.b"]);
     }
 
     #[test]
-    fn test_use_with_insert_overwrite_directory() {
-        let query = r#"-- test set queue name and mutil line comment  select * from test.111111 
-                       set tez.queue.name=root.test; 
-                       use test_db; INSERT OVERWRITE DIRECTORY '/output/path' SELECT id, name FROM test.source_table"#;
+    fn test_from_first_select_without_insert() {
+        let query = r#"from test.src s select s.id where s.v in (select v from test.cfg)"#;
         let mut processor = HiveSqlParser::new();
         processor.parse(query).unwrap();
-        let table_names = processor.get_table_names();
-        assert_eq!(table_names.len(), 1);
-        assert!(table_names.contains(&"test.source_table".to_string()));
+        assert_eq!(processor.get_table_names(), vec!["test.src", "test.cfg"]);
+        assert!(processor.get_target_table_names().is_empty());
+
+        let mut processor = HiveSqlParser::new();
+        processor.parse("from test.src select *").unwrap();
+        assert_eq!(processor.get_table_names(), vec!["test.src"]);
+    }
+
+    #[test]
+    fn test_extract_raw_table_tokens_insert_into_table() {
+        let query = r#"insert into table d select * from a join c on a.id = c.id;
+        insert overwrite table test.e select * from default.f"#;
+        assert_eq!(
+            HiveSqlParser::extract_raw_table_tokens(query),
+            vec!["d", "a", "c", "test.e", "default.f"]
+        );
     }
 }
@@ -6,40 +6,91 @@
 use base64::{engine::general_purpose, Engine as _};
 use tauri_demo::HiveSqlParser;
 
-#[tauri::command]
-fn gen_all_source_table(input: String, file_content: Option<String>) -> String {
-    let mut processor = HiveSqlParser::new();
-
-    let query;
+/// 读取前端传入的 SQL：优先使用输入框内容，否则解码 Base64 编码的文件内容
+fn read_query(input: String, file_content: Option<String>) -> Result<String, String> {
     if !input.is_empty() {
-        query = input;
-    } else if let Some(base64_content) = file_content {
-        match general_purpose::STANDARD.decode(base64_content) {
-            Ok(decoded_content) => {
-                 // 尝试将 Vec<u8> 转换为 String
-    match String::from_utf8(decoded_content) {
-        Ok(string) => query = string,
-        Err(e) => return format!("Failed to convert: {}", e),
+        return Ok(input);
     }
-            }
-            Err(_) => {
-                return "Failed to decode Base64 content".to_string();
-            }
+    let Some(base64_content) = file_content else {
+        return Err("No input provided".to_string());
+    };
+    match general_purpose::STANDARD.decode(base64_content) {
+        // 尝试将 Vec<u8> 转换为 String
+        Ok(decoded_content) => {
+            String::from_utf8(decoded_content).map_err(|e| format!("Failed to convert: {}", e))
         }
-    } else {
-        return "No input provided".to_string()
+        Err(_) => Err("Failed to decode Base64 content".to_string()),
     }
-    let res = processor.parse(query.as_str());
-    if  res.is_ok()  {
-        processor.get_table_names().join("\n")
-    } else {
-        return  format!("error: {:?}", res.err())
+}
+
+#[tauri::command]
+fn gen_all_source_table(
+    input: String,
+    file_content: Option<String>,
+    separator: Option<String>,
+) -> String {
+    let mut processor = HiveSqlParser::new();
+    let query = match read_query(input, file_content) {
+        Ok(query) => query,
+        Err(message) => return message,
+    };
+    match processor.parse(query.as_str()) {
+        Ok(_) => {
+            // 默认每行一个表名，前端可以传入自定义分隔符
+            let separator = separator.unwrap_or_else(|| "\n".to_string());
+            processor.get_table_names().join(&separator)
+        }
+        // 使用 Display 输出，保证前端看到的是英文错误描述
+        Err(e) => format!("error: {}", e),
+    }
+}
+
+/// 返回 SQL 写入的目标表，每行一个
+#[tauri::command]
+fn gen_target_table(input: String, file_content: Option<String>) -> String {
+    let mut processor = HiveSqlParser::new();
+    let query = match read_query(input, file_content) {
+        Ok(query) => query,
+        Err(message) => return message,
+    };
+    match processor.parse(query.as_str()) {
+        Ok(_) => processor.get_target_table_names().join("\n"),
+        Err(e) => format!("error: {}", e),
     }
 }
 
 fn main() {
     tauri::Builder::default()
-        .invoke_handler(tauri::generate_handler![gen_all_source_table])
+        .invoke_handler(tauri::generate_handler![
+            gen_all_source_table,
+            gen_target_table
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gen_all_source_table_with_separator() {
+        let query = "select t1.id from test.table1 t1 join test.table2 t2 on t1.id = t2.id";
+        let result = gen_all_source_table(query.to_string(), None, Some(",".to_string()));
+        assert_eq!(result, "test.table1,test.table2");
+    }
+
+    #[test]
+    fn test_gen_all_source_table_default_separator() {
+        let query = "select * from test.table1 union all select * from test.table2";
+        let result = gen_all_source_table(query.to_string(), None, None);
+        assert_eq!(result, "test.table1\ntest.table2");
+    }
+
+    #[test]
+    fn test_gen_target_table_insert_overwrite() {
+        let query = "insert overwrite table test.target select * from test.source";
+        let result = gen_target_table(query.to_string(), None);
+        assert_eq!(result, "test.target");
+    }
+}
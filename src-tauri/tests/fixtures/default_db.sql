@@ -0,0 +1,5 @@
+-- 没有 USE 语句，未指定数据库的表名使用 --default-db 指定的数据库
+insert overwrite table mart.daily_summary
+select o.id, c.name
+from orders o
+join dim.customer c on o.customer_id = c.id;
@@ -0,0 +1,74 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+fn temp_sql_file(name: &str, content: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("{}_{}.hql", name, std::process::id()));
+    fs::write(&path, content).unwrap();
+    path
+}
+
+#[test]
+fn test_cli_parses_file() {
+    let path = temp_sql_file(
+        "parse_hive_cli_ok",
+        "use test_db;\nselect * from table1 t1 join test.table2 t2 on t1.id = t2.id;\n",
+    );
+    let output = Command::new(env!("CARGO_BIN_EXE_parse_hive"))
+        .arg(&path)
+        .output()
+        .unwrap();
+    fs::remove_file(&path).unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "test_db.table1\ntest.table2\n"
+    );
+}
+
+#[test]
+fn test_cli_reads_stdin() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_parse_hive"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"select * from test.my_table")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "test.my_table\n");
+}
+
+#[test]
+fn test_cli_fails_on_parse_error() {
+    let path = temp_sql_file("parse_hive_cli_err", "select from where;");
+    let output = Command::new(env!("CARGO_BIN_EXE_parse_hive"))
+        .arg(&path)
+        .output()
+        .unwrap();
+    fs::remove_file(&path).unwrap();
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn test_cli_default_db_flag() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/default_db.sql");
+    let output = Command::new(env!("CARGO_BIN_EXE_parse_hive"))
+        .args(["--default-db", "ods"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "ods.orders\ndim.customer\n"
+    );
+}